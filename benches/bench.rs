@@ -6,9 +6,10 @@
 extern crate test;
 
 use rand::{distributions::Standard, prelude::SmallRng, seq::SliceRandom, Rng, SeedableRng};
+use std::collections::VecDeque;
 use std::mem;
 use test::Bencher;
-use timsort::sort;
+use timsort::{sort, sort_deque};
 
 type BigSortable = (u64, u64, u64, u64);
 
@@ -156,3 +157,36 @@ fn sort_strings(b: &mut Bencher) {
     });
     b.bytes = bytes as u64;
 }
+
+/// A deque of `v`, wrapped around its buffer at the middle. The benchmarks
+/// build a new one each time, since sorting it can change where it wraps.
+fn wrapped_deque(v: &[u64]) -> VecDeque<u64> {
+    let mut d = VecDeque::with_capacity(v.len());
+    for _ in 0..d.capacity() - v.len() / 2 {
+        d.push_back(0);
+        d.pop_front();
+    }
+    d.extend(v);
+    d
+}
+
+#[bench]
+fn sort_deque_wrapped(b: &mut Bencher) {
+    let v: Vec<u64> = rng().sample_iter(Standard).take(20_000).collect();
+    b.iter(|| {
+        let mut d = wrapped_deque(&v);
+        sort_deque(&mut d);
+    });
+    b.bytes = (v.len() * mem::size_of::<u64>()) as u64;
+}
+
+/// The same as `sort_deque_wrapped`, by making the deque contiguous first.
+#[bench]
+fn sort_deque_contiguous(b: &mut Bencher) {
+    let v: Vec<u64> = rng().sample_iter(Standard).take(20_000).collect();
+    b.iter(|| {
+        let mut d = wrapped_deque(&v);
+        sort(d.make_contiguous());
+    });
+    b.bytes = (v.len() * mem::size_of::<u64>()) as u64;
+}
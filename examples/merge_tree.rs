@@ -0,0 +1,219 @@
+//! Draws the merge tree of a sort as an SVG image, to show what the sort did
+//! with a particular input.
+//!
+//! ```text
+//! cargo run --example merge_tree -- random 100000 > tree.svg
+//! cargo run --example merge_tree -- --trace saved.txt > tree.svg
+//! cargo run --example merge_tree -- partial 10000 --text > saved.txt
+//! ```
+//!
+//! The inputs are `random`, `sorted`, `reversed`, `partial` (sorted, with a
+//! few random swaps) and `few-unique`. Runs found in the input are drawn along
+//! the bottom: blue if they were ascending, orange if they were reversed, and
+//! green if insertion sort lengthened them. Each merge is drawn above the two
+//! runs it merged, darker if it galloped.
+
+use rand::{distributions::Standard, prelude::SmallRng, seq::SliceRandom, Rng, SeedableRng};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::{env, fs, process};
+use timsort::{sort_by_observed, Trace, TraceEvent};
+
+const WIDTH: f64 = 1200.0;
+const ROW: f64 = 36.0;
+const MARGIN: f64 = 20.0;
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: merge_tree <random|sorted|reversed|partial|few-unique> [len] [--text|--json]\n       \
+         merge_tree --trace <file>"
+    );
+    process::exit(2)
+}
+
+fn input(kind: &str, len: usize) -> Vec<u64> {
+    let mut rng = SmallRng::seed_from_u64(1);
+    match kind {
+        "random" => (&mut rng).sample_iter(Standard).take(len).collect(),
+        "sorted" => (0..len as u64).collect(),
+        "reversed" => (0..len as u64).rev().collect(),
+        "partial" => {
+            let mut v: Vec<u64> = (0..len as u64).collect();
+            for _ in 0..len / 100 {
+                let (a, b) = (rng.gen_range(0, len), rng.gen_range(0, len));
+                v.swap(a, b);
+            }
+            v
+        }
+        "few-unique" => {
+            let mut v: Vec<u64> = (0..len as u64).map(|i| i % 10).collect();
+            v.shuffle(&mut rng);
+            v
+        }
+        _ => usage(),
+    }
+}
+
+/// A run or merge to draw, with its level in the tree; runs are level 0.
+struct Block {
+    pos: usize,
+    len: usize,
+    level: usize,
+    fill: &'static str,
+    label: String,
+    /// The blocks this one was merged from.
+    children: Vec<usize>,
+}
+
+fn render(trace: &Trace) -> String {
+    let mut blocks: Vec<Block> = Vec::new();
+    // The top block at each position, as an index into `blocks`.
+    let mut top: BTreeMap<usize, usize> = BTreeMap::new();
+    for event in trace.events() {
+        match *event {
+            TraceEvent::Run { pos, len, reversed } => {
+                top.insert(pos, blocks.len());
+                blocks.push(Block {
+                    pos,
+                    len,
+                    level: 0,
+                    fill: if reversed { "#fdae6b" } else { "#9ecae1" },
+                    label: format!("run of {} at {}", len, pos),
+                    children: Vec::new(),
+                });
+            }
+            TraceEvent::Extend { pos, len, new_len } => {
+                let i = *top.entry(pos).or_insert_with(|| {
+                    blocks.push(Block {
+                        pos,
+                        len,
+                        level: 0,
+                        fill: "",
+                        label: String::new(),
+                        children: Vec::new(),
+                    });
+                    blocks.len() - 1
+                });
+                blocks[i].len = new_len;
+                blocks[i].fill = "#a1d99b";
+                blocks[i].label = format!("run of {} at {}, extended to {}", len, pos, new_len);
+            }
+            TraceEvent::Merge {
+                pos,
+                first_len,
+                second_len,
+                gallops,
+            } => {
+                let children: Vec<usize> = [pos, pos + first_len]
+                    .iter()
+                    .filter_map(|p| top.remove(p))
+                    .collect();
+                let level = children.iter().map(|&c| blocks[c].level).max().unwrap_or(0) + 1;
+                top.insert(pos, blocks.len());
+                blocks.push(Block {
+                    pos,
+                    len: first_len + second_len,
+                    level,
+                    fill: if gallops > 0 { "#756bb1" } else { "#bcbddc" },
+                    label: format!(
+                        "merge of {} and {} at {}, galloped {} times",
+                        first_len, second_len, pos, gallops
+                    ),
+                    children,
+                });
+            }
+        }
+    }
+
+    let len = blocks
+        .iter()
+        .map(|b| b.pos + b.len)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let levels = blocks.iter().map(|b| b.level).max().unwrap_or(0) + 1;
+    let scale = (WIDTH - 2.0 * MARGIN) / len as f64;
+    let height = levels as f64 * ROW + 2.0 * MARGIN;
+    let x = |pos: usize| MARGIN + pos as f64 * scale;
+    let y = |level: usize| MARGIN + (levels - 1 - level) as f64 * ROW;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="sans-serif" font-size="10">"#,
+        WIDTH, height
+    );
+    for block in &blocks {
+        for &c in &block.children {
+            let child = &blocks[c];
+            let _ = writeln!(
+                svg,
+                r##"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="#999"/>"##,
+                x(child.pos) + child.len as f64 * scale / 2.0,
+                y(child.level),
+                x(block.pos) + block.len as f64 * scale / 2.0,
+                y(block.level) + ROW / 2.0,
+            );
+        }
+    }
+    for block in &blocks {
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}" stroke="white" stroke-width="0.5"><title>{}</title></rect>"#,
+            x(block.pos),
+            y(block.level),
+            (block.len as f64 * scale).max(0.5),
+            ROW / 2.0,
+            block.fill,
+            block.label,
+        );
+    }
+    let runs = blocks.iter().filter(|b| b.level == 0).count();
+    let _ = writeln!(
+        svg,
+        r#"<text x="{}" y="{}">{} elements, {} runs, {} merges, {} levels</text>"#,
+        MARGIN,
+        MARGIN - 6.0,
+        len,
+        runs,
+        blocks.len() - runs,
+        levels
+    );
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let trace = match args.first().map(String::as_str) {
+        None => usage(),
+        Some("--trace") => {
+            let path = args.get(1).unwrap_or_else(|| usage());
+            let text = fs::read_to_string(path).unwrap_or_else(|e| {
+                eprintln!("{}: {}", path, e);
+                process::exit(1)
+            });
+            text.parse::<Trace>().unwrap_or_else(|e| {
+                eprintln!("{}: {}", path, e);
+                process::exit(1)
+            })
+        }
+        Some(kind) => {
+            let len = match args.get(1).filter(|a| !a.starts_with("--")) {
+                Some(len) => len.parse().unwrap_or_else(|_| usage()),
+                None => 10_000,
+            };
+            let mut list = input(kind, len);
+            let mut trace = Trace::new();
+            sort_by_observed(&mut list, |a, b| a.cmp(b), &mut trace);
+            trace
+        }
+    };
+    if args.iter().any(|a| a == "--text") {
+        print!("{}", trace);
+    } else if args.iter().any(|a| a == "--json") {
+        println!("{}", trace.to_json());
+    } else {
+        print!("{}", render(&trace));
+    }
+}
@@ -0,0 +1,51 @@
+/*
+ * C interface to the timsort crate. Build the library with
+ *
+ *     cargo rustc --release --features capi --crate-type staticlib
+ *
+ * (or `--crate-type cdylib` for a shared library), and link against it.
+ * The crate's tests compile these declarations alongside the ones in
+ * src/capi.rs, and CI links tests/capi.c against the library.
+ */
+
+#ifndef TIMSORT_H
+#define TIMSORT_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The sort succeeded. */
+#define TIMSORT_OK 0
+/* An argument was invalid: a null pointer, or a total size that overflows. */
+#define TIMSORT_EINVAL 1
+/*
+ * Memory for the sort couldn't be allocated. The array holds the same
+ * elements as before, in an unspecified order.
+ */
+#define TIMSORT_ENOMEM 2
+
+/* A comparison function, with the same signature as for glibc's qsort_r. */
+typedef int (*timsort_cmp)(const void *a, const void *b, void *ctx);
+
+/*
+ * Stably sort `n` elements of `size` bytes each, starting at `base`.
+ *
+ * `cmp` is passed pointers to two elements and `ctx`, and returns a negative,
+ * zero or positive value, like for qsort_r. The elements are moved with
+ * memcpy, so they must be trivially relocatable. Returns one of the
+ * TIMSORT_* status codes.
+ *
+ * Merges allocate scratch space as they need it, for at most n / 2 elements,
+ * and the sort keeps a small stack of the sorted runs waiting to be merged.
+ * If either allocation fails, the sort stops and returns TIMSORT_ENOMEM.
+ */
+int timsort_sort(void *base, size_t n, size_t size, timsort_cmp cmp, void *ctx);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TIMSORT_H */
@@ -0,0 +1,211 @@
+//! Sorting while checking that the comparator really is a total order.
+
+#[cfg(test)]
+mod tests;
+
+use crate::{apply_permutation, sort_by};
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::cmp::Ordering;
+use core::fmt;
+
+/// A way in which a comparator was caught not being a total order. Indices
+/// are positions in the list before it was sorted, except for `NotSorted`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// Comparing the element at `index` with itself didn't give `Equal`.
+    Reflexivity { index: usize, ordering: Ordering },
+    /// Comparing `a` with `b` gave `forward`, but comparing `b` with `a` gave
+    /// `backward`, which isn't its reverse.
+    Antisymmetry {
+        a: usize,
+        b: usize,
+        forward: Ordering,
+        backward: Ordering,
+    },
+    /// The orderings between `a`, `b` and `c` contradict each other, such as
+    /// `a < b` and `b < c` but not `a < c`.
+    Transitivity {
+        a: usize,
+        b: usize,
+        c: usize,
+        ab: Ordering,
+        bc: Ordering,
+        ac: Ordering,
+    },
+    /// The sorted list has an element greater than the one after it, at
+    /// `index` in the output.
+    NotSorted { index: usize },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Violation::Reflexivity { index, ordering } => write!(
+                f,
+                "element {} compared with itself gives {:?}",
+                index, ordering
+            ),
+            Violation::Antisymmetry {
+                a,
+                b,
+                forward,
+                backward,
+            } => write!(
+                f,
+                "elements {} and {} compare as {:?}, but as {:?} the other way around",
+                a, b, forward, backward
+            ),
+            Violation::Transitivity {
+                a,
+                b,
+                c,
+                ab,
+                bc,
+                ac,
+            } => write!(
+                f,
+                "elements {}, {} and {} aren't transitive: {:?}, {:?}, but {:?} from first to last",
+                a, b, c, ab, bc, ac
+            ),
+            Violation::NotSorted { index } => {
+                write!(f, "output isn't sorted at {}", index)
+            }
+        }
+    }
+}
+
+/// What `sort_by_audited` found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditReport {
+    /// Comparisons made by the sort itself.
+    pub comparisons: usize,
+    /// Extra comparisons made to check the sampled ones.
+    pub checks: usize,
+    /// The first problem found, if any.
+    pub violation: Option<Violation>,
+}
+
+impl AuditReport {
+    /// Whether no problems were found.
+    #[inline]
+    pub fn is_ok(&self) -> bool {
+        self.violation.is_none()
+    }
+}
+
+/// Whether `ac` could be the ordering between `a` and `c`, given the orderings
+/// `ab` and `bc` through `b`.
+fn transitive(ab: Ordering, bc: Ordering, ac: Ordering) -> bool {
+    use Ordering::*;
+    match (ab, bc) {
+        (Equal, Equal) => ac == Equal,
+        (Less, Less) | (Less, Equal) | (Equal, Less) => ac == Less,
+        (Greater, Greater) | (Greater, Equal) | (Equal, Greater) => ac == Greater,
+        (Less, Greater) | (Greater, Less) => true,
+    }
+}
+
+struct Auditor {
+    comparisons: usize,
+    violation: Option<Violation>,
+    /// An element from the last sampled comparison, to make triples with.
+    prev: Option<usize>,
+}
+
+/// Sorts the list like `sort_by`, checking that `cmp` behaves like a total
+/// order along the way, for debugging comparators.
+///
+/// One comparison in every `sample_every` is checked: both elements are
+/// compared with themselves and in the other order, and with an element from
+/// the previous check for transitivity. Afterwards, the output is checked to
+/// be sorted. The report holds the first problem found, if any. The list is
+/// sorted as well as the comparator allows either way.
+///
+/// The sort works on indices, so it needs memory for one `usize` per element.
+///
+/// # Panics
+///
+/// Panics if `sample_every` is 0.
+pub fn sort_by_audited<T, C: Fn(&T, &T) -> Ordering>(
+    list: &mut [T],
+    cmp: C,
+    sample_every: usize,
+) -> AuditReport {
+    assert!(sample_every != 0, "sample_every must be at least 1");
+    let auditor = RefCell::new(Auditor {
+        comparisons: 0,
+        violation: None,
+        prev: None,
+    });
+    let checks = Cell::new(0);
+    let check = |a: usize, b: usize, ab: Ordering| -> Option<Violation> {
+        let cmp = |x: usize, y: usize| {
+            checks.set(checks.get() + 1);
+            cmp(&list[x], &list[y])
+        };
+        for &index in &[a, b] {
+            let ordering = cmp(index, index);
+            if ordering != Ordering::Equal {
+                return Some(Violation::Reflexivity { index, ordering });
+            }
+        }
+        let backward = cmp(b, a);
+        if backward != ab.reverse() {
+            return Some(Violation::Antisymmetry {
+                a,
+                b,
+                forward: ab,
+                backward,
+            });
+        }
+        let c = match auditor.borrow().prev {
+            Some(c) if c != a && c != b => c,
+            _ => return None,
+        };
+        let (bc, ac) = (cmp(b, c), cmp(a, c));
+        if !transitive(ab, bc, ac) {
+            return Some(Violation::Transitivity {
+                a,
+                b,
+                c,
+                ab,
+                bc,
+                ac,
+            });
+        }
+        None
+    };
+
+    let mut perm: Vec<usize> = (0..list.len()).collect();
+    sort_by(&mut perm, |&a, &b| {
+        let ab = cmp(&list[a], &list[b]);
+        let mut state = auditor.borrow_mut();
+        state.comparisons += 1;
+        if state.violation.is_none() && state.comparisons % sample_every == 0 {
+            drop(state);
+            let violation = check(a, b, ab);
+            state = auditor.borrow_mut();
+            state.violation = violation;
+            state.prev = Some(a);
+        }
+        ab
+    });
+    let mut state = auditor.into_inner();
+
+    if state.violation.is_none() {
+        state.violation = perm
+            .windows(2)
+            .position(|w| {
+                checks.set(checks.get() + 1);
+                cmp(&list[w[0]], &list[w[1]]) == Ordering::Greater
+            })
+            .map(|index| Violation::NotSorted { index });
+    }
+    apply_permutation(list, &mut perm);
+    AuditReport {
+        comparisons: state.comparisons,
+        checks: checks.get(),
+        violation: state.violation,
+    }
+}
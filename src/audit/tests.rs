@@ -0,0 +1,122 @@
+//! Comparator audits.
+
+use super::{AuditReport, Violation};
+use crate::sort_by_audited;
+use crate::test_util::shuffled;
+use core::cmp::Ordering;
+use std::cell::Cell;
+
+#[test]
+fn total_order() {
+    let mut list = shuffled(1000);
+    let report = sort_by_audited(&mut list, |a, b| a.cmp(b), 1);
+    assert!(report.is_ok(), "{:?}", report);
+    assert!(report.checks > report.comparisons);
+    assert!(list.iter().enumerate().all(|(i, &x)| i as u32 == x));
+}
+
+#[test]
+fn sampled() {
+    let mut list = shuffled(1000);
+    let every = sort_by_audited(&mut list.clone(), |a, b| a.cmp(b), 1);
+    let some = sort_by_audited(&mut list, |a, b| a.cmp(b), 10);
+    assert_eq!(some.comparisons, every.comparisons);
+    assert!(some.checks < every.checks);
+}
+
+#[test]
+fn reflexivity() {
+    // `partial_cmp` on NaN, forced into an `Ordering`.
+    let mut list = vec![1.0, f64::NAN, 0.0];
+    let report = sort_by_audited(
+        &mut list,
+        |a, b| a.partial_cmp(b).unwrap_or(Ordering::Less),
+        1,
+    );
+    assert_eq!(
+        report.violation,
+        Some(Violation::Reflexivity {
+            index: 1,
+            ordering: Ordering::Less
+        })
+    );
+}
+
+#[test]
+fn antisymmetry() {
+    // Never admits to anything being greater.
+    let mut list = vec![3, 1, 2];
+    let report = sort_by_audited(&mut list, |a, b| a.cmp(b).min(Ordering::Equal), 1);
+    match report.violation {
+        Some(Violation::Antisymmetry {
+            forward, backward, ..
+        }) => assert_ne!(forward, backward.reverse()),
+        v => panic!("{:?}", v),
+    }
+}
+
+#[test]
+fn transitivity() {
+    // Rock, paper, scissors.
+    let beats = |a: &u8, b: &u8| {
+        if a == b {
+            Ordering::Equal
+        } else if (a + 1) % 3 == *b {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        }
+    };
+    let mut list = vec![0, 1, 2, 0, 1, 2];
+    let report = sort_by_audited(&mut list, beats, 1);
+    match report.violation {
+        Some(Violation::Transitivity { ab, bc, ac, .. }) => {
+            assert!(!super::transitive(ab, bc, ac))
+        }
+        v => panic!("{:?}", v),
+    }
+    let mut sorted = list.clone();
+    sorted.sort_unstable();
+    assert_eq!(sorted, [0, 0, 1, 1, 2, 2]);
+}
+
+#[test]
+fn not_sorted() {
+    // Changes its mind once the sort is done. None of the comparisons are
+    // sampled, so only the final check can notice.
+    let calls = Cell::new(0);
+    let mut list = vec![3, 1, 2];
+    let report = sort_by_audited(
+        &mut list,
+        |_: &u32, _: &u32| {
+            calls.set(calls.get() + 1);
+            if calls.get() > 2 {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            }
+        },
+        usize::MAX,
+    );
+    assert_eq!(report.comparisons, 2);
+    assert_eq!(report.violation, Some(Violation::NotSorted { index: 0 }));
+    assert_eq!(list, [3, 1, 2]);
+}
+
+#[test]
+fn display() {
+    let report = AuditReport {
+        comparisons: 0,
+        checks: 0,
+        violation: Some(Violation::Antisymmetry {
+            a: 1,
+            b: 2,
+            forward: Ordering::Less,
+            backward: Ordering::Less,
+        }),
+    };
+    assert_eq!(
+        report.violation.unwrap().to_string(),
+        "elements 1 and 2 compare as Less, but as Less the other way around"
+    );
+}
@@ -0,0 +1,173 @@
+//! Sorting of fixed-width records whose size is only known at runtime, like
+//! C's `qsort`. The records are plain bytes, and are moved with byte copies.
+//!
+//! The records are sorted as a `ScratchContainer`, so runs are found and
+//! extended by the same code as for slices. The merges are the container
+//! ones, because `merge` moves elements of a type known at compile time, and
+//! a record's size isn't.
+
+#[cfg(test)]
+mod tests;
+
+use crate::container::{sort_container_with_scratch, ScratchContainer, SortContainer};
+#[cfg(feature = "capi")]
+use crate::container::{try_sort_container_with_scratch, NoMemory};
+use alloc::collections::TryReserveError;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::slice;
+
+/// A block of scratch space, aligned like C's `max_align_t`.
+#[derive(Copy, Clone)]
+#[repr(C, align(16))]
+struct Block([u8; 16]);
+
+/// Scratch space for records. It's aligned so that a record in scratch space
+/// is as aligned as it was in the slice, for comparators that cast records to
+/// structs.
+struct Scratch {
+    blocks: Vec<Block>,
+}
+
+impl Scratch {
+    fn new() -> Self {
+        Scratch { blocks: Vec::new() }
+    }
+
+    /// Make sure there are at least `len` bytes.
+    fn try_grow(&mut self, len: usize) -> Result<(), TryReserveError> {
+        let blocks = (len + 15) / 16;
+        if blocks > self.blocks.len() {
+            self.blocks.try_reserve_exact(blocks - self.blocks.len())?;
+            self.blocks.resize(blocks, Block([0; 16]));
+        }
+        Ok(())
+    }
+
+    fn bytes(&self) -> &[u8] {
+        // SAFETY: `Block` is plain bytes with no padding.
+        unsafe { slice::from_raw_parts(self.blocks.as_ptr() as *const u8, self.blocks.len() * 16) }
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        // SAFETY: as for `bytes`.
+        unsafe {
+            slice::from_raw_parts_mut(self.blocks.as_mut_ptr() as *mut u8, self.blocks.len() * 16)
+        }
+    }
+}
+
+/// The records of a byte slice, with scratch space for merging.
+struct Records<'a, F> {
+    data: &'a mut [u8],
+    size: usize,
+    scratch: Scratch,
+    cmp: F,
+}
+
+impl<'a, F: Fn(&[u8], &[u8]) -> Ordering> Records<'a, F> {
+    #[inline]
+    fn record(&self, i: usize) -> &[u8] {
+        &self.data[i * self.size..][..self.size]
+    }
+}
+
+impl<'a, F: Fn(&[u8], &[u8]) -> Ordering> SortContainer for Records<'a, F> {
+    fn len(&self) -> usize {
+        self.data.len() / self.size
+    }
+    fn compare(&self, i: usize, j: usize) -> Ordering {
+        (self.cmp)(self.record(i), self.record(j))
+    }
+    fn swap(&mut self, i: usize, j: usize) {
+        if i == j {
+            return;
+        }
+        let (lo, hi) = (i.min(j), i.max(j));
+        let (first, second) = self.data.split_at_mut(hi * self.size);
+        first[lo * self.size..][..self.size].swap_with_slice(&mut second[..self.size]);
+    }
+}
+
+impl<'a, F: Fn(&[u8], &[u8]) -> Ordering> ScratchContainer for Records<'a, F> {
+    /// If the scratch space can't be allocated, merges fall back to rotating
+    /// records in place.
+    fn reserve_scratch(&mut self, len: usize) -> bool {
+        match len.checked_mul(self.size) {
+            Some(bytes) => self.scratch.try_grow(bytes).is_ok(),
+            None => false,
+        }
+    }
+    fn copy_to_scratch(&mut self, i: usize, slot: usize) {
+        let size = self.size;
+        self.scratch.bytes_mut()[slot * size..][..size]
+            .copy_from_slice(&self.data[i * size..][..size]);
+    }
+    fn copy_from_scratch(&mut self, slot: usize, i: usize) {
+        let size = self.size;
+        self.data[i * size..][..size].copy_from_slice(&self.scratch.bytes()[slot * size..][..size]);
+    }
+    fn compare_scratch(&self, slot: usize, i: usize) -> Ordering {
+        (self.cmp)(
+            &self.scratch.bytes()[slot * self.size..][..self.size],
+            self.record(i),
+        )
+    }
+    fn move_element(&mut self, src: usize, dest: usize) {
+        let size = self.size;
+        self.data
+            .copy_within(src * size..(src + 1) * size, dest * size);
+    }
+}
+
+/// Sorts `data` as a sequence of records that are `elem_size` bytes each,
+/// using `cmp` to compare two records.
+///
+/// ```
+/// // Records of a big-endian u16 key followed by a one-byte payload.
+/// let mut data = [0, 9, b'a', 0, 2, b'b', 1, 0, b'c', 0, 2, b'd'];
+/// timsort::sort_bytes(&mut data, 3, |a, b| a[..2].cmp(&b[..2]));
+/// assert_eq!(data, [0, 2, b'b', 0, 2, b'd', 0, 9, b'a', 1, 0, b'c']);
+/// ```
+///
+/// # Panics
+///
+/// Panics if the length of `data` isn't a multiple of `elem_size`, unless
+/// `elem_size` is 0. Zero-sized records can't be told apart, so then `data`
+/// is left as it is, whatever its length.
+pub fn sort_bytes<F: Fn(&[u8], &[u8]) -> Ordering>(data: &mut [u8], elem_size: usize, cmp: F) {
+    if elem_size == 0 {
+        // There's nothing to move.
+        return;
+    }
+    assert!(
+        data.len() % elem_size == 0,
+        "data length is not a multiple of the element size"
+    );
+    sort_container_with_scratch(&mut Records {
+        data,
+        size: elem_size,
+        scratch: Scratch::new(),
+        cmp,
+    });
+}
+
+/// Like `sort_bytes`, but if scratch space for a merge can't be allocated,
+/// stops with an error rather than falling back to merging in place, and
+/// likewise if the stack of runs can't grow. `data` is then left as a
+/// permutation of what it was. `elem_size` must not be
+/// zero.
+#[cfg(feature = "capi")]
+pub(crate) fn try_sort_bytes<F: Fn(&[u8], &[u8]) -> Ordering>(
+    data: &mut [u8],
+    elem_size: usize,
+    cmp: F,
+) -> Result<(), NoMemory> {
+    debug_assert!(elem_size != 0 && data.len() % elem_size == 0);
+    try_sort_container_with_scratch(&mut Records {
+        data,
+        size: elem_size,
+        scratch: Scratch::new(),
+        cmp,
+    })
+}
@@ -0,0 +1,72 @@
+use super::sort_bytes;
+use crate::test_util::keys;
+
+/// Records with a 3-byte key and a 4-byte sequence number, so stability can
+/// be checked.
+fn records(len: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(len * 7);
+    for (i, key) in keys(len, 1009).enumerate() {
+        let key = key as u32;
+        data.extend_from_slice(&key.to_be_bytes()[1..]);
+        data.extend_from_slice(&(i as u32).to_be_bytes());
+    }
+    data
+}
+
+#[test]
+fn sorted() {
+    for &len in &[0, 1, 2, 30, 64, 500, 3000] {
+        let mut data = records(len);
+        let mut expected: Vec<&[u8]> = Vec::new();
+        let original = data.clone();
+        expected.extend(original.chunks(7));
+        expected.sort_by(|a, b| a[..3].cmp(&b[..3]));
+        sort_bytes(&mut data, 7, |a, b| a[..3].cmp(&b[..3]));
+        assert_eq!(data, expected.concat());
+    }
+}
+
+#[test]
+fn descending_runs() {
+    let mut data: Vec<u8> = (0..=255).rev().chain(0..=255).collect();
+    sort_bytes(&mut data, 1, |a, b| a.cmp(b));
+    let mut expected: Vec<u8> = (0..=255).chain(0..=255).collect();
+    expected.sort();
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn zero_size() {
+    let mut data = [3, 2, 1];
+    sort_bytes(&mut data, 0, |_, _| unreachable!());
+    assert_eq!(data, [3, 2, 1]);
+}
+
+#[test]
+#[should_panic(expected = "not a multiple")]
+fn bad_length() {
+    sort_bytes(&mut [1, 2, 3], 2, |a, b| a.cmp(b));
+}
+
+/// Records are merged with galloping, so merging two runs that interleave
+/// in long blocks takes far fewer comparisons than merging them one at a
+/// time would.
+#[test]
+fn galloping() {
+    // Two sorted runs, each made of blocks of 100 that alternate between
+    // them.
+    let key = |run: u32, i: u32| (i / 100 * 200 + run * 100 + i % 100).to_be_bytes();
+    let mut data: Vec<u8> = (0..2000).flat_map(|i| key(0, i)).collect();
+    data.extend((0..2000).flat_map(|i| key(1, i)));
+    let compares = std::cell::Cell::new(0);
+    sort_bytes(&mut data, 4, |a, b| {
+        compares.set(compares.get() + 1);
+        a.cmp(b)
+    });
+    let expected: Vec<u8> = (0..4000u32).flat_map(u32::to_be_bytes).collect();
+    assert_eq!(data, expected);
+    // Finding the two runs takes 3998 comparisons, and merging them one at a
+    // time would take 3999 more. Galloping takes about 500.
+    let merge = compares.get() - 3998;
+    assert!(merge < 1000, "{}", merge);
+}
@@ -0,0 +1,97 @@
+//! Stopping a sort partway through, for sorts that must finish by a deadline.
+
+#[cfg(test)]
+mod tests;
+
+use crate::error::{try_sort_by_checked, SortError, SortErrorKind};
+use core::cell::Cell;
+use core::cmp::Ordering;
+use core::convert::Infallible;
+use core::sync::atomic::{self, AtomicBool};
+
+/// What a comparison wrapped by `try_sort_by_until` can fail with.
+enum Stop<E> {
+    Error(E),
+    Cancelled,
+}
+
+/// Sort the list, checking `stop` before every comparison and stopping with
+/// `SortErrorKind::Cancelled` once it says so.
+fn try_sort_by_until<T, E, C, S>(list: &mut [T], cmp: C, stop: S) -> Result<(), SortError<E>>
+where
+    C: Fn(&T, &T) -> Result<Ordering, E>,
+    S: Fn() -> bool,
+{
+    let stopping = |a: &T, b: &T| {
+        if stop() {
+            Err(Stop::Cancelled)
+        } else {
+            cmp(a, b).map_err(Stop::Error)
+        }
+    };
+    try_sort_by_checked(list, stopping).map_err(|err| {
+        err.map_comparator(|e| match e {
+            Stop::Error(e) => SortErrorKind::Comparator(e),
+            Stop::Cancelled => SortErrorKind::Cancelled,
+        })
+    })
+}
+
+/// Sorts the list like `try_sort_by_checked`, but makes at most `budget`
+/// comparisons. If the sort needs more than that, it stops with
+/// `SortErrorKind::Cancelled` and leaves the list as a permutation of what it
+/// was.
+///
+/// A list of `n` elements that's already sorted takes `n - 1` comparisons.
+pub fn try_sort_by_with_budget<T, E, C: Fn(&T, &T) -> Result<Ordering, E>>(
+    list: &mut [T],
+    cmp: C,
+    budget: usize,
+) -> Result<(), SortError<E>> {
+    let left = Cell::new(budget);
+    try_sort_by_until(list, cmp, || match left.get().checked_sub(1) {
+        Some(n) => {
+            left.set(n);
+            false
+        }
+        None => true,
+    })
+}
+
+#[inline]
+pub fn sort_by_with_budget<T, C: Fn(&T, &T) -> Ordering>(
+    list: &mut [T],
+    cmp: C,
+    budget: usize,
+) -> Result<(), SortError<Infallible>> {
+    try_sort_by_with_budget(
+        list,
+        move |a, b| -> Result<_, Infallible> { Ok(cmp(a, b)) },
+        budget,
+    )
+}
+
+/// Sorts the list like `try_sort_by_checked`, but stops with
+/// `SortErrorKind::Cancelled` once `cancel` is set, such as by another thread
+/// when a deadline passes. The flag is checked before every comparison, and
+/// the list is left as a permutation of what it was.
+pub fn try_sort_by_cancellable<T, E, C: Fn(&T, &T) -> Result<Ordering, E>>(
+    list: &mut [T],
+    cmp: C,
+    cancel: &AtomicBool,
+) -> Result<(), SortError<E>> {
+    try_sort_by_until(list, cmp, || cancel.load(atomic::Ordering::Relaxed))
+}
+
+#[inline]
+pub fn sort_by_cancellable<T, C: Fn(&T, &T) -> Ordering>(
+    list: &mut [T],
+    cmp: C,
+    cancel: &AtomicBool,
+) -> Result<(), SortError<Infallible>> {
+    try_sort_by_cancellable(
+        list,
+        move |a, b| -> Result<_, Infallible> { Ok(cmp(a, b)) },
+        cancel,
+    )
+}
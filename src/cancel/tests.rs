@@ -0,0 +1,82 @@
+//! Cancelling sorts.
+
+use crate::test_util::shuffled;
+use crate::{sort_by_cancellable, sort_by_with_budget, try_sort_by_with_budget, SortErrorKind};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+
+fn assert_permutation(list: &[u32]) {
+    let mut sorted = list.to_vec();
+    sorted.sort_unstable();
+    assert!(sorted.iter().enumerate().all(|(i, &x)| i as u32 == x));
+}
+
+#[test]
+fn budget() {
+    for budget in [0, 1, 100, 2000, 8000] {
+        let calls = Cell::new(0);
+        let mut list = shuffled(1000);
+        let err = sort_by_with_budget(
+            &mut list,
+            |a, b| {
+                calls.set(calls.get() + 1);
+                a.cmp(b)
+            },
+            budget,
+        )
+        .unwrap_err();
+        assert!(matches!(err.kind(), SortErrorKind::Cancelled));
+        assert_eq!(calls.get(), budget);
+        assert_permutation(&list);
+        // The comparison it stopped before is recorded, as for a failed one.
+        assert!(err.progress().compared.is_some());
+        if budget == 0 {
+            assert_eq!(err.progress().compared, Some((Some(0), Some(1))));
+        }
+    }
+}
+
+#[test]
+fn budget_enough() {
+    let mut list: Vec<u32> = (0..1000).collect();
+    assert!(sort_by_with_budget(&mut list, |a, b| a.cmp(b), 999).is_ok());
+    let mut list = shuffled(1000);
+    assert!(sort_by_with_budget(&mut list, |a, b| a.cmp(b), 1_000_000).is_ok());
+    assert!(list.iter().enumerate().all(|(i, &x)| i as u32 == x));
+}
+
+#[test]
+fn budget_error() {
+    let mut list = shuffled(1000);
+    let err = try_sort_by_with_budget(&mut list, |_, _| Err("fail"), 10).unwrap_err();
+    assert!(matches!(err.kind(), SortErrorKind::Comparator("fail")));
+}
+
+#[test]
+fn cancel() {
+    let cancel = AtomicBool::new(false);
+    let calls = Cell::new(0);
+    let mut list = shuffled(1000);
+    let err = sort_by_cancellable(
+        &mut list,
+        |a, b| {
+            calls.set(calls.get() + 1);
+            if calls.get() == 3000 {
+                cancel.store(true, AtomicOrdering::Relaxed);
+            }
+            a.cmp(b)
+        },
+        &cancel,
+    )
+    .unwrap_err();
+    assert!(matches!(err.kind(), SortErrorKind::Cancelled));
+    assert_eq!(err.to_string().split(',').next(), Some("sort cancelled"));
+    assert_eq!(calls.get(), 3000);
+    assert_permutation(&list);
+
+    // It's checked before the first comparison too.
+    let mut list = shuffled(1000);
+    let err = sort_by_cancellable(&mut list, |a, b| a.cmp(b), &cancel).unwrap_err();
+    assert_eq!(err.progress().compared, Some((Some(0), Some(1))));
+    assert_eq!(list, shuffled(1000));
+}
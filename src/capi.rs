@@ -0,0 +1,74 @@
+//! A C ABI, enabled by the `capi` feature. The declarations for C and C++
+//! are in `include/timsort.h`.
+
+#[cfg(test)]
+mod tests;
+
+use crate::bytes::try_sort_bytes;
+use core::ffi::{c_int, c_void};
+use core::slice;
+
+/// The sort succeeded.
+pub const TIMSORT_OK: c_int = 0;
+/// An argument was invalid: a null pointer, or a total size that overflows.
+pub const TIMSORT_EINVAL: c_int = 1;
+/// Memory for the sort couldn't be allocated. The array holds the same
+/// elements as before, in an unspecified order.
+pub const TIMSORT_ENOMEM: c_int = 2;
+
+/// A comparison function, with the same signature as for glibc's `qsort_r`.
+pub type TimsortCmp =
+    unsafe extern "C" fn(a: *const c_void, b: *const c_void, ctx: *mut c_void) -> c_int;
+
+/// Stably sort `n` elements of `size` bytes each, starting at `base`.
+///
+/// `cmp` is passed pointers to two elements and `ctx`, and returns a
+/// negative, zero or positive value, like for `qsort_r`. The elements are
+/// moved with `memcpy`, so they must be trivially relocatable.
+///
+/// Merges allocate scratch space as they need it, for at most `n / 2`
+/// elements, and the sort keeps a small stack of the sorted runs waiting to
+/// be merged. If either allocation fails, the sort stops and returns
+/// `TIMSORT_ENOMEM`.
+///
+/// # Safety
+///
+/// `base` must point to `n * size` bytes that are valid for reads and writes,
+/// or may be null if `n` or `size` is zero. `cmp` must be safe to call with
+/// pointers to any two elements and `ctx`, and must not unwind.
+#[no_mangle]
+pub unsafe extern "C" fn timsort_sort(
+    base: *mut c_void,
+    n: usize,
+    size: usize,
+    cmp: Option<TimsortCmp>,
+    ctx: *mut c_void,
+) -> c_int {
+    let cmp = match cmp {
+        Some(cmp) => cmp,
+        None => return TIMSORT_EINVAL,
+    };
+    let len = match n.checked_mul(size) {
+        Some(len) if len <= isize::MAX as usize => len,
+        _ => return TIMSORT_EINVAL,
+    };
+    if n < 2 || size == 0 {
+        return TIMSORT_OK;
+    }
+    if base.is_null() {
+        return TIMSORT_EINVAL;
+    }
+    let data = slice::from_raw_parts_mut(base as *mut u8, len);
+    let ret = try_sort_bytes(data, size, |a, b| {
+        let ord = cmp(
+            a.as_ptr() as *const c_void,
+            b.as_ptr() as *const c_void,
+            ctx,
+        );
+        ord.cmp(&0)
+    });
+    match ret {
+        Ok(()) => TIMSORT_OK,
+        Err(_) => TIMSORT_ENOMEM,
+    }
+}
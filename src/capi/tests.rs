@@ -0,0 +1,188 @@
+use super::{timsort_sort, TimsortCmp, TIMSORT_EINVAL, TIMSORT_ENOMEM, TIMSORT_OK};
+use crate::test_util::{fail_allocs_over, keys};
+use core::ffi::{c_int, c_void};
+use std::env;
+use std::io::Write;
+use std::mem;
+use std::process::{Command, Stdio};
+use std::ptr;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Record {
+    key: f64,
+    seq: u32,
+}
+
+/// Compare records by key, counting calls in the context.
+unsafe extern "C" fn cmp_records(a: *const c_void, b: *const c_void, ctx: *mut c_void) -> c_int {
+    *(ctx as *mut usize) += 1;
+    let (a, b) = (&*(a as *const Record), &*(b as *const Record));
+    a.key.partial_cmp(&b.key).unwrap() as c_int
+}
+
+#[test]
+fn sort_records() {
+    let mut list: Vec<Record> = keys(1000, 101)
+        .zip(0..)
+        .map(|(key, seq)| Record {
+            key: key as f64,
+            seq,
+        })
+        .collect();
+    let mut expected = list.clone();
+    expected.sort_by(|a, b| a.key.partial_cmp(&b.key).unwrap());
+    let mut calls = 0usize;
+    let ret = unsafe {
+        timsort_sort(
+            list.as_mut_ptr() as *mut c_void,
+            list.len(),
+            std::mem::size_of::<Record>(),
+            Some(cmp_records),
+            &mut calls as *mut usize as *mut c_void,
+        )
+    };
+    assert_eq!(ret, TIMSORT_OK);
+    assert_eq!(list, expected);
+    assert!(calls > 0);
+}
+
+/// If a merge can't allocate scratch space, the sort returns an error, and
+/// the array still holds every record once.
+#[test]
+fn out_of_memory() {
+    // Two long runs, which need a long merge.
+    let mut list: Vec<Record> = (0..500)
+        .map(|x| 2 * x)
+        .chain((0..500).map(|x| 2 * x + 1))
+        .zip(0..)
+        .map(|(key, seq)| Record {
+            key: key as f64,
+            seq,
+        })
+        .collect();
+    let mut calls = 0usize;
+    let ret = fail_allocs_over(1000, || unsafe {
+        timsort_sort(
+            list.as_mut_ptr() as *mut c_void,
+            list.len(),
+            std::mem::size_of::<Record>(),
+            Some(cmp_records),
+            &mut calls as *mut usize as *mut c_void,
+        )
+    });
+    assert_eq!(ret, TIMSORT_ENOMEM);
+    let mut seqs: Vec<u32> = list.iter().map(|r| r.seq).collect();
+    seqs.sort_unstable();
+    assert!(seqs.iter().copied().eq(0..1000));
+}
+
+/// If the stack of runs can't be allocated either, the sort returns an error
+/// before it merges anything.
+#[test]
+fn out_of_memory_for_runs() {
+    let mut list: Vec<Record> = keys(1000, 101)
+        .zip(0..)
+        .map(|(key, seq)| Record {
+            key: key as f64,
+            seq,
+        })
+        .collect();
+    let mut calls = 0usize;
+    let ret = fail_allocs_over(0, || unsafe {
+        timsort_sort(
+            list.as_mut_ptr() as *mut c_void,
+            list.len(),
+            std::mem::size_of::<Record>(),
+            Some(cmp_records),
+            &mut calls as *mut usize as *mut c_void,
+        )
+    });
+    assert_eq!(ret, TIMSORT_ENOMEM);
+    let mut seqs: Vec<u32> = list.iter().map(|r| r.seq).collect();
+    seqs.sort_unstable();
+    assert!(seqs.iter().copied().eq(0..1000));
+}
+
+#[test]
+fn invalid() {
+    let mut calls = 0usize;
+    let ctx = &mut calls as *mut usize as *mut c_void;
+    unsafe {
+        assert_eq!(
+            timsort_sort(ptr::null_mut(), 0, 8, Some(cmp_records), ctx),
+            TIMSORT_OK
+        );
+        assert_eq!(
+            timsort_sort(ptr::null_mut(), 10, 8, Some(cmp_records), ctx),
+            TIMSORT_EINVAL
+        );
+        assert_eq!(
+            timsort_sort(ptr::null_mut(), 10, 8, None, ctx),
+            TIMSORT_EINVAL
+        );
+        let mut x = 0u8;
+        let base = &mut x as *mut u8 as *mut c_void;
+        assert_eq!(
+            timsort_sort(base, usize::MAX, 2, Some(cmp_records), ctx),
+            TIMSORT_EINVAL
+        );
+    }
+    assert_eq!(calls, 0);
+}
+
+/// `include/timsort.h` compiles, and declares the same constants, type and
+/// function as this module. The C below restates the Rust declarations, and
+/// the C compiler checks them against the header; a typedef or prototype
+/// that doesn't match is a conflicting redeclaration.
+#[test]
+fn header() {
+    // The declarations restated below, as Rust types.
+    let cmp: Option<TimsortCmp> = None;
+    let _: Option<unsafe extern "C" fn(*const c_void, *const c_void, *mut c_void) -> c_int> = cmp;
+    let _: unsafe extern "C" fn(
+        *mut c_void,
+        usize,
+        usize,
+        Option<TimsortCmp>,
+        *mut c_void,
+    ) -> c_int = timsort_sort;
+    let source = format!(
+        r#"#include "timsort.h"
+_Static_assert(TIMSORT_OK == {}, "TIMSORT_OK");
+_Static_assert(TIMSORT_EINVAL == {}, "TIMSORT_EINVAL");
+_Static_assert(TIMSORT_ENOMEM == {}, "TIMSORT_ENOMEM");
+_Static_assert(sizeof(size_t) == {}, "size_t");
+_Static_assert(sizeof(int) == {}, "int");
+typedef int (*timsort_cmp)(const void *a, const void *b, void *ctx);
+int timsort_sort(void *base, size_t n, size_t size, timsort_cmp cmp, void *ctx);
+"#,
+        TIMSORT_OK,
+        TIMSORT_EINVAL,
+        TIMSORT_ENOMEM,
+        mem::size_of::<usize>(),
+        mem::size_of::<c_int>(),
+    );
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".into());
+    let mut child = Command::new(&cc)
+        .args([
+            "-std=c11",
+            "-Wall",
+            "-Werror",
+            "-fsyntax-only",
+            "-x",
+            "c",
+            "-",
+        ])
+        .arg(concat!("-I", env!("CARGO_MANIFEST_DIR"), "/include"))
+        .stdin(Stdio::piped())
+        .spawn()
+        .unwrap_or_else(|e| panic!("can't run the C compiler `{}`: {}", cc, e));
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(source.as_bytes())
+        .unwrap();
+    assert!(child.wait().unwrap().success(), "{}", source);
+}
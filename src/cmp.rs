@@ -0,0 +1,135 @@
+//! Ready-made comparators to pass to `sort_by` and friends.
+
+#[cfg(test)]
+mod tests;
+
+mod spec;
+
+use core::cmp::Ordering;
+pub use spec::{Direction, Nulls, SortSpec};
+
+/// How to break ties between numbers that are equal apart from their leading
+/// zeros, such as `"07"` and `"7"`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LeadingZeros {
+    /// Treat them as equal.
+    Ignore,
+    /// If the strings are otherwise equal, the one with fewer leading zeros
+    /// sorts first.
+    FewerFirst,
+    /// If the strings are otherwise equal, the one with more leading zeros
+    /// sorts first.
+    MoreFirst,
+}
+
+/// A "natural" or "human" string ordering, where runs of ASCII digits are
+/// compared by their numeric value: `"file2"` sorts before `"file10"`.
+///
+/// ```
+/// use timsort::cmp::NaturalOrder;
+///
+/// let mut files = ["file10", "File2", "file1"];
+/// let order = NaturalOrder::new().case_insensitive(true);
+/// timsort::sort_by(&mut files, |a, b| order.compare(a, b));
+/// assert_eq!(files, ["file1", "File2", "file10"]);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NaturalOrder {
+    case_insensitive: bool,
+    leading_zeros: LeadingZeros,
+}
+
+impl Default for NaturalOrder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NaturalOrder {
+    /// A case-sensitive ordering that ignores leading zeros.
+    pub const fn new() -> Self {
+        NaturalOrder {
+            case_insensitive: false,
+            leading_zeros: LeadingZeros::Ignore,
+        }
+    }
+
+    /// Compare letters by their lowercase forms.
+    pub const fn case_insensitive(mut self, yes: bool) -> Self {
+        self.case_insensitive = yes;
+        self
+    }
+
+    /// Choose how numbers differing only in leading zeros are ordered.
+    pub const fn leading_zeros(mut self, leading_zeros: LeadingZeros) -> Self {
+        self.leading_zeros = leading_zeros;
+        self
+    }
+
+    /// Compare two strings.
+    pub fn compare(&self, mut a: &str, mut b: &str) -> Ordering {
+        // The leading zeros tie-breaker only matters if nothing else differs,
+        // so remember the first one and keep going.
+        let mut tiebreak = Ordering::Equal;
+        loop {
+            let (ca, cb) = match (a.chars().next(), b.chars().next()) {
+                (None, None) => return tiebreak,
+                (None, Some(_)) => return Ordering::Less,
+                (Some(_), None) => return Ordering::Greater,
+                (Some(ca), Some(cb)) => (ca, cb),
+            };
+            if ca.is_ascii_digit() && cb.is_ascii_digit() {
+                let (da, ra) = split_digits(a);
+                let (db, rb) = split_digits(b);
+                let ord = cmp_digits(da, db);
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+                if tiebreak == Ordering::Equal {
+                    tiebreak = match self.leading_zeros {
+                        LeadingZeros::Ignore => Ordering::Equal,
+                        LeadingZeros::FewerFirst => da.len().cmp(&db.len()),
+                        LeadingZeros::MoreFirst => db.len().cmp(&da.len()),
+                    };
+                }
+                a = ra;
+                b = rb;
+            } else {
+                let ord = if self.case_insensitive {
+                    ca.to_lowercase().cmp(cb.to_lowercase())
+                } else {
+                    ca.cmp(&cb)
+                };
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+                a = &a[ca.len_utf8()..];
+                b = &b[cb.len_utf8()..];
+            }
+        }
+    }
+}
+
+/// Split a string into its leading run of ASCII digits and the rest.
+fn split_digits(s: &str) -> (&str, &str) {
+    let end = s
+        .bytes()
+        .position(|c| !c.is_ascii_digit())
+        .unwrap_or(s.len());
+    s.split_at(end)
+}
+
+/// Compare two runs of digits by value, without parsing them, so there's no
+/// limit on how long they can be.
+fn cmp_digits(a: &str, b: &str) -> Ordering {
+    let a = a.trim_start_matches('0');
+    let b = b.trim_start_matches('0');
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+/// Compare two strings in natural order, with the default options of
+/// `NaturalOrder`.
+#[inline]
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    NaturalOrder::new().compare(a, b)
+}
@@ -0,0 +1,141 @@
+//! Multi-key sort specifications, like the "sort by" dialog of a spreadsheet.
+
+#[cfg(test)]
+mod tests;
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+/// Which way a key is sorted.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Ascending,
+    Descending,
+}
+
+/// Where missing (`None`) keys go. This doesn't depend on the direction, so
+/// `Nulls::Last` means last for both ascending and descending keys.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Nulls {
+    First,
+    Last,
+}
+
+type KeyCmp<'a, T> = Box<dyn Fn(&T, &T) -> Ordering + 'a>;
+
+/// A list of sort keys, each with its own direction, combined into a single
+/// comparator. Later keys only break ties left by earlier ones.
+///
+/// ```
+/// use timsort::cmp::SortSpec;
+///
+/// struct Employee {
+///     dept: u32,
+///     salary: u32,
+///     name: &'static str,
+/// }
+/// let mut staff = vec![
+///     Employee { dept: 2, salary: 10, name: "bob" },
+///     Employee { dept: 1, salary: 20, name: "eve" },
+///     Employee { dept: 2, salary: 30, name: "amy" },
+///     Employee { dept: 2, salary: 10, name: "al" },
+/// ];
+/// let spec = SortSpec::by_key(|e: &Employee| e.dept)
+///     .then_desc(|e| e.salary)
+///     .then_by(|a, b| a.name.cmp(b.name));
+/// spec.sort(&mut staff);
+/// let names: Vec<_> = staff.iter().map(|e| e.name).collect();
+/// assert_eq!(names, ["eve", "amy", "al", "bob"]);
+/// ```
+pub struct SortSpec<'a, T> {
+    keys: Vec<KeyCmp<'a, T>>,
+}
+
+impl<'a, T: 'a> SortSpec<'a, T> {
+    /// Start with a comparison function.
+    pub fn by<F: Fn(&T, &T) -> Ordering + 'a>(cmp: F) -> Self {
+        SortSpec { keys: Vec::new() }.then_by(cmp)
+    }
+
+    /// Start with an ascending key. Like `slice::sort_by_key`, the key
+    /// function is called on every comparison.
+    pub fn by_key<K: Ord, F: Fn(&T) -> K + 'a>(key: F) -> Self {
+        Self::by(move |a, b| key(a).cmp(&key(b)))
+    }
+
+    /// Start with a descending key.
+    pub fn by_key_desc<K: Ord, F: Fn(&T) -> K + 'a>(key: F) -> Self {
+        Self::by(move |a, b| key(b).cmp(&key(a)))
+    }
+
+    /// Start with a key that may be missing.
+    pub fn by_nullable<K: Ord, F: Fn(&T) -> Option<K> + 'a>(
+        key: F,
+        direction: Direction,
+        nulls: Nulls,
+    ) -> Self {
+        Self::by(move |a, b| cmp_nullable(key(a), key(b), direction, nulls))
+    }
+
+    /// Break ties with a comparison function.
+    pub fn then_by<F: Fn(&T, &T) -> Ordering + 'a>(mut self, cmp: F) -> Self {
+        self.keys.push(Box::new(cmp));
+        self
+    }
+
+    /// Break ties with an ascending key.
+    pub fn then<K: Ord, F: Fn(&T) -> K + 'a>(self, key: F) -> Self {
+        self.then_by(move |a, b| key(a).cmp(&key(b)))
+    }
+
+    /// Break ties with a descending key.
+    pub fn then_desc<K: Ord, F: Fn(&T) -> K + 'a>(self, key: F) -> Self {
+        self.then_by(move |a, b| key(b).cmp(&key(a)))
+    }
+
+    /// Break ties with a key that may be missing.
+    pub fn then_nullable<K: Ord, F: Fn(&T) -> Option<K> + 'a>(
+        self,
+        key: F,
+        direction: Direction,
+        nulls: Nulls,
+    ) -> Self {
+        self.then_by(move |a, b| cmp_nullable(key(a), key(b), direction, nulls))
+    }
+
+    /// Compare two elements by each key in turn.
+    pub fn compare(&self, a: &T, b: &T) -> Ordering {
+        for key in &self.keys {
+            match key(a, b) {
+                Ordering::Equal => {}
+                ord => return ord,
+            }
+        }
+        Ordering::Equal
+    }
+
+    /// Sort the list by this specification. Elements equal on every key keep
+    /// their original order.
+    pub fn sort(&self, list: &mut [T]) {
+        crate::sort_by(list, |a, b| self.compare(a, b))
+    }
+}
+
+/// Compare two optional keys.
+fn cmp_nullable<K: Ord>(
+    a: Option<K>,
+    b: Option<K>,
+    direction: Direction,
+    nulls: Nulls,
+) -> Ordering {
+    match (a, b) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) if nulls == Nulls::First => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) if nulls == Nulls::First => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) if direction == Direction::Ascending => a.cmp(&b),
+        (Some(a), Some(b)) => b.cmp(&a),
+    }
+}
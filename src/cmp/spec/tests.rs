@@ -0,0 +1,87 @@
+use super::{Direction, Nulls, SortSpec};
+use core::cmp::Ordering;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Row {
+    dept: &'static str,
+    salary: u32,
+    bonus: Option<u32>,
+    id: usize,
+}
+
+fn rows() -> Vec<Row> {
+    let depts = ["ops", "dev", "hr"];
+    (0..150)
+        .map(|id| Row {
+            dept: depts[id % 3],
+            salary: (id % 7) as u32 * 1000,
+            bonus: if id % 4 == 0 {
+                None
+            } else {
+                Some((id % 5) as u32)
+            },
+            id,
+        })
+        .collect()
+}
+
+#[test]
+fn multiple_keys() {
+    let mut list = rows();
+    SortSpec::by_key(|r: &Row| r.dept)
+        .then_desc(|r| r.salary)
+        .sort(&mut list);
+    for pair in list.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        assert!(a.dept <= b.dept);
+        if a.dept == b.dept {
+            assert!(a.salary >= b.salary);
+            if a.salary == b.salary {
+                // Stable on full ties.
+                assert!(a.id < b.id);
+            }
+        }
+    }
+}
+
+#[test]
+fn then_by() {
+    let spec = SortSpec::by_key_desc(|r: &Row| r.salary).then_by(|a, b| b.id.cmp(&a.id));
+    let mut list = rows();
+    spec.sort(&mut list);
+    let mut expected = rows();
+    crate::sort_by(&mut expected, |a, b| {
+        b.salary.cmp(&a.salary).then_with(|| b.id.cmp(&a.id))
+    });
+    assert_eq!(list, expected);
+}
+
+#[test]
+fn nulls() {
+    let mut list = rows();
+    SortSpec::by_nullable(|r: &Row| r.bonus, Direction::Descending, Nulls::Last).sort(&mut list);
+    let nulls = list.iter().filter(|r| r.bonus.is_none()).count();
+    assert!(list[list.len() - nulls..].iter().all(|r| r.bonus.is_none()));
+    for pair in list[..list.len() - nulls].windows(2) {
+        assert!(pair[0].bonus >= pair[1].bonus);
+    }
+
+    let spec = SortSpec::by_key(|r: &Row| r.dept).then_nullable(
+        |r| r.bonus,
+        Direction::Ascending,
+        Nulls::First,
+    );
+    let a = Row {
+        dept: "dev",
+        salary: 0,
+        bonus: None,
+        id: 0,
+    };
+    let b = Row {
+        bonus: Some(0),
+        ..a.clone()
+    };
+    assert_eq!(spec.compare(&a, &b), Ordering::Less);
+    assert_eq!(spec.compare(&b, &a), Ordering::Greater);
+    assert_eq!(spec.compare(&a, &a), Ordering::Equal);
+}
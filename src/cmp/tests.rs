@@ -0,0 +1,65 @@
+use super::{natural_cmp, LeadingZeros, NaturalOrder};
+use core::cmp::Ordering;
+
+fn sorted<'a>(list: &[&'a str], order: NaturalOrder) -> Vec<&'a str> {
+    let mut list = list.to_vec();
+    crate::sort_by(&mut list, |a, b| order.compare(a, b));
+    list
+}
+
+#[test]
+fn numbers() {
+    let list = ["file10", "file2", "file1", "file", "file02b", "file2a"];
+    assert_eq!(
+        sorted(&list, NaturalOrder::new()),
+        ["file", "file1", "file2", "file2a", "file02b", "file10"]
+    );
+}
+
+#[test]
+fn versions() {
+    let list = ["1.10.0", "1.9.2", "1.9.10", "0.99", "1.9"];
+    assert_eq!(
+        sorted(&list, NaturalOrder::new()),
+        ["0.99", "1.9", "1.9.2", "1.9.10", "1.10.0"]
+    );
+}
+
+/// Digit runs longer than any integer type still compare correctly.
+#[test]
+fn huge_numbers() {
+    let a = "x123456789012345678901234567890";
+    let b = "x99999999999999999999999999999";
+    assert_eq!(natural_cmp(a, b), Ordering::Greater);
+    assert_eq!(natural_cmp(b, a), Ordering::Less);
+}
+
+#[test]
+fn case() {
+    let list = ["b", "B", "a", "A"];
+    assert_eq!(sorted(&list, NaturalOrder::new()), ["A", "B", "a", "b"]);
+    // Case-insensitive equal strings keep their original order.
+    assert_eq!(
+        sorted(&list, NaturalOrder::new().case_insensitive(true)),
+        ["a", "A", "b", "B"]
+    );
+    assert_eq!(
+        NaturalOrder::new()
+            .case_insensitive(true)
+            .compare("ÄPFEL", "äpfel"),
+        Ordering::Equal
+    );
+}
+
+#[test]
+fn leading_zeros() {
+    let list = ["x007", "x7", "x07"];
+    assert_eq!(sorted(&list, NaturalOrder::new()), ["x007", "x7", "x07"]);
+    let fewer = NaturalOrder::new().leading_zeros(LeadingZeros::FewerFirst);
+    assert_eq!(sorted(&list, fewer), ["x7", "x07", "x007"]);
+    let more = NaturalOrder::new().leading_zeros(LeadingZeros::MoreFirst);
+    assert_eq!(sorted(&list, more), ["x007", "x07", "x7"]);
+    // The tie-breaker only applies if the rest of the strings are equal.
+    assert_eq!(fewer.compare("x07b", "x7c"), Ordering::Less);
+    assert_eq!(fewer.compare("x07", "x7"), Ordering::Greater);
+}
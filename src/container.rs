@@ -0,0 +1,506 @@
+//! Sorting for storage that isn't a slice: memory-mapped pages, ring buffers,
+//! columns split into chunks and so on. Like Go's `sort.Interface`, the
+//! container only has to compare and swap elements by index.
+//!
+//! The sort is the same as for slices: runs are found and lengthened with
+//! insertion sort, using the same searches as `find_run` and `insort`, and
+//! merged on the same schedule. Only the moves differ, since a container
+//! can't hand out references to its elements or move them one at a time.
+//!
+//! That's also why the merges are written again here rather than shared
+//! with `merge`, which moves elements through a buffer by pointer. Containers
+//! that can hold a few elements on the side implement `ScratchContainer` as
+//! well, and their merges move elements through that scratch space and
+//! gallop, as `merge` does. Otherwise, merges rotate blocks of elements into
+//! place with swaps, which needs no memory but makes O(n log n) swaps per
+//! merge.
+
+#[cfg(test)]
+mod tests;
+
+use crate::find_run::find_run_by;
+use crate::gallop::{gallop, Mode};
+use crate::insort::insertion_point;
+use crate::merge::MIN_GALLOP;
+use crate::sort::{calc_min_merge, Run, RunStack, MIN_MERGE};
+use crate::{never, NeverResult};
+use alloc::alloc::handle_alloc_error;
+use core::alloc::Layout;
+use core::cmp::{min, Ordering};
+
+/// A sequence of elements that can be sorted with `sort_container`.
+pub trait SortContainer {
+    /// The number of elements.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Compare the elements at `i` and `j`.
+    fn compare(&self, i: usize, j: usize) -> Ordering;
+
+    /// Swap the elements at `i` and `j`.
+    fn swap(&mut self, i: usize, j: usize);
+}
+
+/// A container with scratch space for elements, which
+/// `sort_container_with_scratch` merges through.
+///
+/// Scratch elements behave like bitwise moves: once an element has been
+/// copied to scratch, its old position is treated as uninitialized until
+/// something is copied over it. When the sort returns, or unwinds because
+/// `compare` or `compare_scratch` panicked, every element has been copied
+/// back into the container exactly once. Implementations can rely on this to
+/// use `ptr::read` and `ptr::write` for elements that aren't `Copy`, as long
+/// as the methods that copy don't panic.
+pub trait ScratchContainer: SortContainer {
+    /// Make room for at least `len` elements of scratch space. Returns `false`
+    /// if there isn't room, in which case the merge rotates elements with
+    /// `swap` instead.
+    fn reserve_scratch(&mut self, len: usize) -> bool;
+
+    /// Copy the element at `i` into scratch slot `slot`.
+    fn copy_to_scratch(&mut self, i: usize, slot: usize);
+
+    /// Copy the element in scratch slot `slot` to position `i`.
+    fn copy_from_scratch(&mut self, slot: usize, i: usize);
+
+    /// Compare the element in scratch slot `slot` with the element at `i`.
+    fn compare_scratch(&self, slot: usize, i: usize) -> Ordering;
+
+    /// Copy the element at `src` to `dest`.
+    fn move_element(&mut self, src: usize, dest: usize);
+}
+
+/// Sorts the container, using only `compare` and `swap`. The sort is stable,
+/// and allocates nothing but the stack of runs waiting to be merged.
+#[inline]
+pub fn sort_container<S: SortContainer + ?Sized>(data: &mut S) {
+    sort_runs(
+        data,
+        |layout| handle_alloc_error(layout),
+        |data, lo, mid, hi| -> NeverResult<_> {
+            if let Some((lo, hi)) = trim(data, lo, mid, hi) {
+                sym_merge(data, lo, mid, hi);
+            }
+            Ok(())
+        },
+    )
+    .unwrap_or_else(never)
+}
+
+/// Sorts the container like `sort_container`, but merges through scratch
+/// space, which takes far fewer writes. Each merge asks for scratch space
+/// for the shorter of the two runs, after skipping the elements at either
+/// end that are already in place, so at most half the container.
+#[inline]
+pub fn sort_container_with_scratch<S: ScratchContainer + ?Sized>(data: &mut S) {
+    sort_runs(
+        data,
+        |layout| handle_alloc_error(layout),
+        |data, lo, mid, hi| -> NeverResult<_> {
+            if let Some((lo, hi)) = trim(data, lo, mid, hi) {
+                if data.reserve_scratch(min(mid - lo, hi - mid)) {
+                    merge_scratch(data, lo, mid, hi);
+                } else {
+                    sym_merge(data, lo, mid, hi);
+                }
+            }
+            Ok(())
+        },
+    )
+    .unwrap_or_else(never)
+}
+
+/// A merge couldn't get the scratch space it needed, or the stack of runs
+/// waiting to be merged couldn't grow.
+#[cfg(feature = "capi")]
+pub(crate) struct NoMemory;
+
+/// Sorts the container like `sort_container_with_scratch`, but if
+/// `reserve_scratch` returns `false`, stops instead of rotating. It stops
+/// the same way if the stack of runs can't be allocated, rather than
+/// aborting. The container is then left as a permutation of what it was.
+#[cfg(feature = "capi")]
+pub(crate) fn try_sort_container_with_scratch<S: ScratchContainer + ?Sized>(
+    data: &mut S,
+) -> Result<(), NoMemory> {
+    sort_runs(
+        data,
+        |_| NoMemory,
+        |data, lo, mid, hi| {
+            if let Some((lo, hi)) = trim(data, lo, mid, hi) {
+                if !data.reserve_scratch(min(mid - lo, hi - mid)) {
+                    return Err(NoMemory);
+                }
+                merge_scratch(data, lo, mid, hi);
+            }
+            Ok(())
+        },
+    )
+}
+
+/// Find runs, and merge them with `merge`, which is passed the bounds
+/// `lo..mid` and `mid..hi` of two adjacent sorted runs. If the stack of runs
+/// can't grow, stops with the error `alloc_failed` returns.
+fn sort_runs<S: SortContainer + ?Sized, E>(
+    data: &mut S,
+    mut alloc_failed: impl FnMut(Layout) -> E,
+    mut merge: impl FnMut(&mut S, usize, usize, usize) -> Result<(), E>,
+) -> Result<(), E> {
+    let len = data.len();
+    if len < MIN_MERGE {
+        insertion_sort(data, 0, 0, len);
+        return Ok(());
+    }
+    let min_run = calc_min_merge(len);
+    let mut runs = RunStack::new();
+    let mut pos = 0;
+    while pos < len {
+        let mut run_len = get_run(data, pos);
+        let run_min_len = min(min_run, len - pos);
+        if run_len < run_min_len {
+            insertion_sort(data, pos, pos + run_len, pos + run_min_len);
+            run_len = run_min_len;
+        }
+        runs.push(
+            Run { pos, len: run_len },
+            &mut alloc_failed,
+            &mut |run1, run2| merge(data, run1.pos, run2.pos, run2.pos + run2.len),
+        )?;
+        pos += run_len;
+    }
+    runs.merge_force_collapse(&mut |run1, run2| {
+        merge(data, run1.pos, run2.pos, run2.pos + run2.len)
+    })
+}
+
+/// The first index in `0..len` that `before` is false for, as found by
+/// `gallop`.
+#[inline]
+fn search(len: usize, mode: Mode, mut before: impl FnMut(usize) -> bool) -> usize {
+    gallop(len, mode, |i| -> NeverResult<_> { Ok(before(i)) }).unwrap_or_else(never)
+}
+
+/// Find the run starting at `lo`, reversing it if it's descending, as
+/// `find_run::get_run` does.
+fn get_run<S: SortContainer + ?Sized>(data: &mut S, lo: usize) -> usize {
+    let (reversed, len) = find_run_by(data.len() - lo, |i| -> NeverResult<_> {
+        Ok(data.compare(lo + i, lo + i + 1) == Ordering::Greater)
+    })
+    .unwrap_or_else(never);
+    if reversed {
+        let (mut i, mut j) = (lo, lo + len - 1);
+        while i < j {
+            data.swap(i, j);
+            i += 1;
+            j -= 1;
+        }
+    }
+    len
+}
+
+/// Insertion sort of `lo..hi`, where `lo..start` is already sorted, as
+/// `insort::sort` does, but moving each element into place with swaps.
+fn insertion_sort<S: SortContainer + ?Sized>(data: &mut S, lo: usize, start: usize, hi: usize) {
+    for i in start.max(lo + 1)..hi {
+        let pos = lo
+            + insertion_point(i - lo, |k| -> NeverResult<_> {
+                Ok(data.compare(lo + k, i) == Ordering::Greater)
+            })
+            .unwrap_or_else(never);
+        for j in (pos..i).rev() {
+            data.swap(j, j + 1);
+        }
+    }
+}
+
+/// Skip the elements of the runs `lo..mid` and `mid..hi` that are already in
+/// place, as `merge` does. Returns the new `lo` and `hi`, or `None` if
+/// everything is in place.
+fn trim<S: SortContainer + ?Sized>(
+    data: &S,
+    lo: usize,
+    mid: usize,
+    hi: usize,
+) -> Option<(usize, usize)> {
+    if lo == mid || mid == hi {
+        return None;
+    }
+    // Elements at the start of the first run that are no bigger than the
+    // start of the second run are already in place, and so are elements at
+    // the end of the second run that are no smaller than the end of the first.
+    let lo = lo
+        + search(mid - lo, Mode::Forward, |k| {
+            data.compare(lo + k, mid) != Ordering::Greater
+        });
+    if lo == mid {
+        return None;
+    }
+    let hi = mid
+        + search(hi - mid, Mode::Reverse, |k| {
+            data.compare(mid - 1, mid + k) == Ordering::Greater
+        });
+    Some((lo, hi))
+}
+
+/// Merge the trimmed runs `lo..mid` and `mid..hi`, moving the shorter one to
+/// scratch space, which must have room for it.
+fn merge_scratch<S: ScratchContainer + ?Sized>(data: &mut S, lo: usize, mid: usize, hi: usize) {
+    if mid - lo <= hi - mid {
+        let first_len = mid - lo;
+        for slot in 0..first_len {
+            data.copy_to_scratch(lo + slot, slot);
+        }
+        MergeLo {
+            data,
+            first_pos: 0,
+            first_len,
+            second_pos: mid,
+            second_end: hi,
+            dest_pos: lo,
+        }
+        .merge()
+    } else {
+        let second_len = hi - mid;
+        for slot in 0..second_len {
+            data.copy_to_scratch(mid + slot, slot);
+        }
+        MergeHi {
+            data,
+            first_start: lo,
+            first_end: mid,
+            second_end: second_len,
+            dest_end: hi,
+        }
+        .merge()
+    }
+}
+
+/// Merge with the first run in scratch space. Like `merge::MergeLo`, the
+/// state is kept in an object so that the scratch space can be emptied back
+/// into the container if the comparator panics.
+struct MergeLo<'a, S: ScratchContainer + ?Sized> {
+    data: &'a mut S,
+    /// The next scratch slot of the first run.
+    first_pos: usize,
+    first_len: usize,
+    second_pos: usize,
+    second_end: usize,
+    dest_pos: usize,
+}
+
+impl<'a, S: ScratchContainer + ?Sized> MergeLo<'a, S> {
+    fn take_first(&mut self, count: usize) {
+        for _ in 0..count {
+            self.data.copy_from_scratch(self.first_pos, self.dest_pos);
+            self.first_pos += 1;
+            self.dest_pos += 1;
+        }
+    }
+
+    fn take_second(&mut self, count: usize) {
+        for _ in 0..count {
+            self.data.move_element(self.second_pos, self.dest_pos);
+            self.second_pos += 1;
+            self.dest_pos += 1;
+        }
+    }
+
+    fn merge(mut self) {
+        let mut first_count = 0;
+        let mut second_count = 0;
+        while self.first_pos < self.first_len && self.second_pos < self.second_end {
+            let (data, first_pos, second_pos) = (&*self.data, self.first_pos, self.second_pos);
+            if (second_count | first_count) < MIN_GALLOP {
+                // One-at-a-time mode.
+                if data.compare_scratch(first_pos, second_pos) == Ordering::Greater {
+                    self.take_second(1);
+                    second_count += 1;
+                    first_count = 0;
+                } else {
+                    self.take_first(1);
+                    first_count += 1;
+                    second_count = 0;
+                }
+            } else {
+                // Galloping mode.
+                second_count = search(self.second_end - second_pos, Mode::Forward, |k| {
+                    data.compare_scratch(first_pos, second_pos + k) == Ordering::Greater
+                });
+                self.take_second(second_count);
+                if self.second_pos < self.second_end {
+                    let (data, second_pos) = (&*self.data, self.second_pos);
+                    first_count = search(self.first_len - first_pos, Mode::Forward, |k| {
+                        data.compare_scratch(first_pos + k, second_pos) != Ordering::Greater
+                    });
+                    self.take_first(first_count);
+                }
+            }
+        }
+    }
+}
+
+impl<'a, S: ScratchContainer + ?Sized> Drop for MergeLo<'a, S> {
+    /// Copy whatever is left in scratch space into the gap before the rest of
+    /// the second run.
+    fn drop(&mut self) {
+        self.take_first(self.first_len - self.first_pos);
+    }
+}
+
+/// Merge with the second run in scratch space, working backwards from the
+/// end. Positions are exclusive ends.
+struct MergeHi<'a, S: ScratchContainer + ?Sized> {
+    data: &'a mut S,
+    first_start: usize,
+    first_end: usize,
+    /// The end of what's left of the second run, in scratch slots.
+    second_end: usize,
+    dest_end: usize,
+}
+
+impl<'a, S: ScratchContainer + ?Sized> MergeHi<'a, S> {
+    fn take_first(&mut self, count: usize) {
+        for _ in 0..count {
+            self.first_end -= 1;
+            self.dest_end -= 1;
+            self.data.move_element(self.first_end, self.dest_end);
+        }
+    }
+
+    fn take_second(&mut self, count: usize) {
+        for _ in 0..count {
+            self.second_end -= 1;
+            self.dest_end -= 1;
+            self.data.copy_from_scratch(self.second_end, self.dest_end);
+        }
+    }
+
+    fn merge(mut self) {
+        let mut first_count = 0;
+        let mut second_count = 0;
+        while self.second_end > 0 && self.first_end > self.first_start {
+            let (data, first_start) = (&*self.data, self.first_start);
+            let (first_end, second_end) = (self.first_end, self.second_end);
+            if (second_count | first_count) < MIN_GALLOP {
+                // One-at-a-time mode.
+                if data.compare_scratch(second_end - 1, first_end - 1) == Ordering::Less {
+                    self.take_first(1);
+                    first_count += 1;
+                    second_count = 0;
+                } else {
+                    self.take_second(1);
+                    second_count += 1;
+                    first_count = 0;
+                }
+            } else {
+                // Galloping mode: take the elements at the end of the first
+                // run that are greater than the last of the second, and then
+                // the elements at the end of the second that aren't less than
+                // the last of the first.
+                let len = first_end - first_start;
+                first_count = len
+                    - search(len, Mode::Reverse, |k| {
+                        data.compare_scratch(second_end - 1, first_start + k) != Ordering::Less
+                    });
+                self.take_first(first_count);
+                if self.first_end > self.first_start {
+                    let (data, first_end) = (&*self.data, self.first_end);
+                    second_count = second_end
+                        - search(second_end, Mode::Reverse, |k| {
+                            data.compare_scratch(k, first_end - 1) == Ordering::Less
+                        });
+                    self.take_second(second_count);
+                }
+            }
+        }
+    }
+}
+
+impl<'a, S: ScratchContainer + ?Sized> Drop for MergeHi<'a, S> {
+    /// Copy whatever is left in scratch space into the gap after the rest of
+    /// the first run.
+    fn drop(&mut self) {
+        self.take_second(self.second_end);
+    }
+}
+
+/// Merge the sorted ranges `lo..mid` and `mid..hi` without scratch space, by
+/// rotating blocks into place. This is the SymMerge algorithm of Kim and
+/// Kutzner, as used by Go's `sort.Stable`.
+fn sym_merge<S: SortContainer + ?Sized>(data: &mut S, lo: usize, mid: usize, hi: usize) {
+    if mid - lo == 1 {
+        // Insert the single element of the first run into the second, after
+        // any that are less than it.
+        let pos = mid
+            + search(hi - mid, Mode::Forward, |k| {
+                data.compare(mid + k, lo) == Ordering::Less
+            });
+        for i in lo..pos - 1 {
+            data.swap(i, i + 1);
+        }
+        return;
+    }
+    if hi - mid == 1 {
+        // Insert the single element of the second run into the first, before
+        // any that are greater than it.
+        let pos = lo
+            + search(mid - lo, Mode::Forward, |k| {
+                data.compare(lo + k, mid) != Ordering::Greater
+            });
+        for i in (pos + 1..=mid).rev() {
+            data.swap(i, i - 1);
+        }
+        return;
+    }
+    let half = lo + (hi - lo) / 2;
+    let n = half + mid;
+    let (mut start, mut end) = if mid > half {
+        (n - hi, half)
+    } else {
+        (lo, mid)
+    };
+    let p = n - 1;
+    while start < end {
+        let c = start + (end - start) / 2;
+        if data.compare(p - c, c) != Ordering::Less {
+            start = c + 1;
+        } else {
+            end = c;
+        }
+    }
+    let end = n - start;
+    if start < mid && mid < end {
+        rotate(data, start, mid, end);
+    }
+    if lo < start && start < half {
+        sym_merge(data, lo, start, half);
+    }
+    if half < end && end < hi {
+        sym_merge(data, half, end, hi);
+    }
+}
+
+/// Rotate `lo..hi` so that the element at `mid` ends up at `lo`, using
+/// block swaps.
+fn rotate<S: SortContainer + ?Sized>(data: &mut S, lo: usize, mid: usize, hi: usize) {
+    let (mut i, mut j) = (mid - lo, hi - mid);
+    while i != j {
+        if i > j {
+            swap_range(data, mid - i, mid, j);
+            i -= j;
+        } else {
+            swap_range(data, mid - i, mid + j - i, i);
+            j -= i;
+        }
+    }
+    swap_range(data, mid - i, mid, i);
+}
+
+fn swap_range<S: SortContainer + ?Sized>(data: &mut S, a: usize, b: usize, len: usize) {
+    for i in 0..len {
+        data.swap(a + i, b + i);
+    }
+}
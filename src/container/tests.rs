@@ -0,0 +1,194 @@
+use super::{sort_container, sort_container_with_scratch, ScratchContainer, SortContainer};
+use crate::test_util::keys;
+use core::cmp::Ordering;
+use std::cell::Cell;
+
+/// A container split into fixed-size chunks, with no scratch space.
+struct Chunked {
+    chunks: Vec<Vec<(u32, usize)>>,
+}
+
+impl Chunked {
+    const CHUNK: usize = 10;
+    fn new(list: &[(u32, usize)]) -> Self {
+        Chunked {
+            chunks: list.chunks(Self::CHUNK).map(|c| c.to_vec()).collect(),
+        }
+    }
+    fn get(&self, i: usize) -> &(u32, usize) {
+        &self.chunks[i / Self::CHUNK][i % Self::CHUNK]
+    }
+    fn to_vec(&self) -> Vec<(u32, usize)> {
+        self.chunks.concat()
+    }
+}
+
+impl SortContainer for Chunked {
+    fn len(&self) -> usize {
+        self.chunks.iter().map(Vec::len).sum()
+    }
+    fn compare(&self, i: usize, j: usize) -> Ordering {
+        self.get(i).0.cmp(&self.get(j).0)
+    }
+    fn swap(&mut self, i: usize, j: usize) {
+        let a = *self.get(i);
+        let b = *self.get(j);
+        self.chunks[i / Self::CHUNK][i % Self::CHUNK] = b;
+        self.chunks[j / Self::CHUNK][j % Self::CHUNK] = a;
+    }
+}
+
+/// A vector with scratch space of limited size, and a comparator that counts
+/// calls and can be set to panic.
+struct WithScratch {
+    list: Vec<(u32, usize)>,
+    scratch: Vec<(u32, usize)>,
+    max_scratch: usize,
+    compares: Cell<usize>,
+    panic_after: usize,
+}
+
+impl WithScratch {
+    fn new(list: &[(u32, usize)]) -> Self {
+        WithScratch {
+            list: list.to_vec(),
+            scratch: Vec::new(),
+            max_scratch: usize::MAX,
+            compares: Cell::new(0),
+            panic_after: usize::MAX,
+        }
+    }
+    fn cmp(&self, a: &(u32, usize), b: &(u32, usize)) -> Ordering {
+        self.compares.set(self.compares.get() + 1);
+        if self.compares.get() > self.panic_after {
+            panic!("Expected panic: this is normal");
+        }
+        a.0.cmp(&b.0)
+    }
+}
+
+impl SortContainer for WithScratch {
+    fn len(&self) -> usize {
+        self.list.len()
+    }
+    fn compare(&self, i: usize, j: usize) -> Ordering {
+        self.cmp(&self.list[i], &self.list[j])
+    }
+    fn swap(&mut self, i: usize, j: usize) {
+        self.list.swap(i, j);
+    }
+}
+
+impl ScratchContainer for WithScratch {
+    fn reserve_scratch(&mut self, len: usize) -> bool {
+        if len > self.max_scratch {
+            return false;
+        }
+        self.scratch.resize(len.max(self.scratch.len()), (0, 0));
+        true
+    }
+    fn copy_to_scratch(&mut self, i: usize, slot: usize) {
+        self.scratch[slot] = self.list[i];
+        // Mark the vacated slot, so a lost element shows up.
+        self.list[i] = (u32::MAX, usize::MAX);
+    }
+    fn copy_from_scratch(&mut self, slot: usize, i: usize) {
+        self.list[i] = self.scratch[slot];
+    }
+    fn compare_scratch(&self, slot: usize, i: usize) -> Ordering {
+        self.cmp(&self.scratch[slot], &self.list[i])
+    }
+    fn move_element(&mut self, src: usize, dest: usize) {
+        self.list[dest] = self.list[src];
+    }
+}
+
+fn input(len: usize) -> Vec<(u32, usize)> {
+    let mut list: Vec<(u32, usize)> = keys(len, 61).map(|k| k as u32).zip(0..).collect();
+    // Add some runs, in both directions.
+    if len > 400 {
+        list[100..250].sort();
+        list[300..400].sort_by_key(|x| std::cmp::Reverse(x.0));
+    }
+    list
+}
+
+fn check_sorted(sorted: &[(u32, usize)], len: usize) {
+    let mut expected = input(len);
+    expected.sort_by_key(|x| x.0);
+    assert_eq!(sorted, &expected[..]);
+}
+
+#[test]
+fn chunked() {
+    for &len in &[0, 1, 2, 5, 63, 64, 65, 200, 1000] {
+        let mut data = Chunked::new(&input(len));
+        sort_container(&mut data);
+        check_sorted(&data.to_vec(), len);
+    }
+}
+
+#[test]
+fn scratch() {
+    for &len in &[0, 1, 2, 5, 63, 64, 65, 200, 1000] {
+        let mut data = WithScratch::new(&input(len));
+        sort_container_with_scratch(&mut data);
+        check_sorted(&data.list, len);
+        assert!(data.scratch.len() <= len / 2);
+    }
+}
+
+/// Merges that can't get enough scratch space rotate instead.
+#[test]
+fn scratch_refused() {
+    for &max_scratch in &[0, 10, 100] {
+        let mut data = WithScratch::new(&input(1000));
+        data.max_scratch = max_scratch;
+        sort_container_with_scratch(&mut data);
+        check_sorted(&data.list, 1000);
+    }
+}
+
+/// Merges through scratch space gallop, so merging two runs that interleave
+/// in long blocks takes far fewer comparisons than one at a time would. The
+/// first run is moved to scratch space when it's the shorter, and the second
+/// run otherwise.
+#[test]
+fn galloping() {
+    for &(first_len, second_len) in &[(2000, 2000), (3000, 1000)] {
+        // Blocks of 100 that alternate between the runs.
+        let key = |run: usize, i: usize| (i / 100 * 200 + run * 100 + i % 100) as u32;
+        let list: Vec<(u32, usize)> = (0..first_len)
+            .map(|i| key(0, i))
+            .chain((0..second_len).map(|i| key(1, i)))
+            .zip(0..)
+            .collect();
+        let mut data = WithScratch::new(&list);
+        sort_container_with_scratch(&mut data);
+        let mut expected = list;
+        expected.sort();
+        assert_eq!(data.list, expected);
+        // Finding the two runs takes one comparison per element, less one.
+        let merge = data.compares.get() - (first_len + second_len - 1);
+        assert!(merge < 1000, "{}", merge);
+    }
+}
+
+/// A panicking comparator leaves every element in the container once.
+#[test]
+fn panic() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    for &panic_after in &[10, 500, 3000, 6000] {
+        let mut data = WithScratch::new(&input(1000));
+        data.panic_after = panic_after;
+        catch_unwind(AssertUnwindSafe(|| sort_container_with_scratch(&mut data)))
+            .err()
+            .unwrap();
+        let mut list = data.list;
+        list.sort();
+        let mut expected = input(1000);
+        expected.sort();
+        assert_eq!(list, expected);
+    }
+}
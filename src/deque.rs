@@ -0,0 +1,123 @@
+//! Sorting sequences that are stored as two slices, such as a `VecDeque`
+//! that has wrapped around, without first making them contiguous.
+
+#[cfg(test)]
+mod tests;
+
+use crate::container::{sort_container_with_scratch, ScratchContainer, SortContainer};
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::marker::PhantomData;
+use core::ptr;
+
+/// Two slices treated as one sequence, by raw pointer, with scratch space for
+/// merging across them.
+struct Split<'a, T, F> {
+    head: *mut T,
+    head_len: usize,
+    tail: *mut T,
+    tail_len: usize,
+    /// Scratch space. Its length is always 0, so the elements copied into it
+    /// are never dropped from there.
+    scratch: Vec<T>,
+    cmp: F,
+    marker: PhantomData<&'a mut [T]>,
+}
+
+impl<'a, T, F> Split<'a, T, F> {
+    fn new(head: &'a mut [T], tail: &'a mut [T], cmp: F) -> Self {
+        Split {
+            head: head.as_mut_ptr(),
+            head_len: head.len(),
+            tail: tail.as_mut_ptr(),
+            tail_len: tail.len(),
+            scratch: Vec::new(),
+            cmp,
+            marker: PhantomData,
+        }
+    }
+
+    /// Where element `i` of the sequence is.
+    #[inline]
+    fn at(&self, i: usize) -> *mut T {
+        debug_assert!(i < self.head_len + self.tail_len);
+        // SAFETY: the sort only asks for elements of the sequence.
+        unsafe {
+            if i < self.head_len {
+                self.head.add(i)
+            } else {
+                self.tail.add(i - self.head_len)
+            }
+        }
+    }
+
+    #[inline]
+    fn slot(&self, slot: usize) -> *mut T {
+        debug_assert!(slot < self.scratch.capacity());
+        // SAFETY: `reserve_scratch` made room for every slot the sort uses.
+        unsafe { self.scratch.as_ptr().add(slot) as *mut T }
+    }
+}
+
+// SAFETY, for all of the below: the container sort only compares elements
+// that are in place, and moves elements as bitwise copies, with every
+// element back in the sequence exactly once when it returns or unwinds. The
+// copies can't panic.
+impl<'a, T, F: Fn(&T, &T) -> Ordering> SortContainer for Split<'a, T, F> {
+    fn len(&self) -> usize {
+        self.head_len + self.tail_len
+    }
+    fn compare(&self, i: usize, j: usize) -> Ordering {
+        unsafe { (self.cmp)(&*self.at(i), &*self.at(j)) }
+    }
+    fn swap(&mut self, i: usize, j: usize) {
+        unsafe { ptr::swap(self.at(i), self.at(j)) }
+    }
+}
+
+impl<'a, T, F: Fn(&T, &T) -> Ordering> ScratchContainer for Split<'a, T, F> {
+    /// If the scratch space can't be allocated, merges fall back to rotating
+    /// elements in place.
+    fn reserve_scratch(&mut self, len: usize) -> bool {
+        self.scratch.try_reserve_exact(len).is_ok()
+    }
+    fn copy_to_scratch(&mut self, i: usize, slot: usize) {
+        unsafe { ptr::copy_nonoverlapping(self.at(i), self.slot(slot), 1) }
+    }
+    fn copy_from_scratch(&mut self, slot: usize, i: usize) {
+        unsafe { ptr::copy_nonoverlapping(self.slot(slot), self.at(i), 1) }
+    }
+    fn compare_scratch(&self, slot: usize, i: usize) -> Ordering {
+        unsafe { (self.cmp)(&*self.slot(slot), &*self.at(i)) }
+    }
+    fn move_element(&mut self, src: usize, dest: usize) {
+        unsafe { ptr::copy(self.at(src), self.at(dest), 1) }
+    }
+}
+
+/// Sorts the concatenation of `head` and `tail` as a single sequence: after
+/// sorting, every element of `head` is less than or equal to every element
+/// of `tail`.
+///
+/// The two slices are sorted as one sequence, without moving them together
+/// first. Runs are found across the join, so a run that crosses from one
+/// slice into the other is found as one run, and merges gallop as `sort`'s
+/// do. Merges need temporary storage for the shorter of the two runs; if it
+/// can't be allocated, they rotate elements in place instead.
+pub fn sort_slices_by<T, F: Fn(&T, &T) -> Ordering>(head: &mut [T], tail: &mut [T], cmp: F) {
+    sort_container_with_scratch(&mut Split::new(head, tail, cmp))
+}
+
+/// Sorts a `VecDeque` where it is in its buffer, even if it has wrapped
+/// around, rather than rotating it contiguous first.
+#[inline]
+pub fn sort_deque_by<T, F: Fn(&T, &T) -> Ordering>(deque: &mut VecDeque<T>, cmp: F) {
+    let (head, tail) = deque.as_mut_slices();
+    sort_slices_by(head, tail, cmp)
+}
+
+#[inline]
+pub fn sort_deque<T: Ord>(deque: &mut VecDeque<T>) {
+    sort_deque_by(deque, Ord::cmp)
+}
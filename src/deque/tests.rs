@@ -0,0 +1,116 @@
+use super::{sort_deque, sort_deque_by, sort_slices_by};
+use crate::test_util::keys;
+use std::collections::VecDeque;
+
+/// Build a deque of strings that has wrapped around its buffer.
+fn wrapped_strings(len: usize) -> VecDeque<String> {
+    let mut deque = VecDeque::with_capacity(len);
+    let cap = deque.capacity();
+    for _ in 0..cap - len / 2 {
+        deque.push_back(String::new());
+        deque.pop_front();
+    }
+    for (i, key) in keys(len, 211).enumerate() {
+        deque.push_back(format!("{:04}-{}", key, i));
+    }
+    assert!(!deque.as_slices().1.is_empty());
+    deque
+}
+
+#[test]
+fn deque() {
+    for &len in &[2, 10, 100, 1000] {
+        let mut deque = wrapped_strings(len);
+        let mut expected: Vec<String> = deque.iter().cloned().collect();
+        expected.sort();
+        sort_deque(&mut deque);
+        assert!(deque.iter().eq(expected.iter()));
+    }
+}
+
+#[test]
+fn stable() {
+    let mut deque = wrapped_strings(500);
+    let key = |s: &String| s[..4].to_string();
+    let mut expected: Vec<String> = deque.iter().cloned().collect();
+    expected.sort_by_key(key);
+    sort_deque_by(&mut deque, |a, b| key(a).cmp(&key(b)));
+    assert!(deque.iter().eq(expected.iter()));
+}
+
+/// A run that crosses the wrap point is found as one run.
+#[test]
+fn run_across_wrap() {
+    let mut head: Vec<u32> = (0..50).collect();
+    let mut tail: Vec<u32> = (50..300).collect();
+    let compares = std::cell::Cell::new(0);
+    sort_slices_by(&mut head, &mut tail, |a, b| {
+        compares.set(compares.get() + 1);
+        a.cmp(b)
+    });
+    assert_eq!(compares.get(), 299);
+    assert!(head.iter().chain(&tail).copied().eq(0..300));
+}
+
+#[test]
+fn empty_halves() {
+    let mut deque: VecDeque<u32> = (0..100).rev().collect();
+    sort_deque(&mut deque);
+    assert!(deque.iter().copied().eq(0..100));
+    let mut head: [u32; 0] = [];
+    let mut tail = [3, 1, 2];
+    sort_slices_by(&mut head, &mut tail, Ord::cmp);
+    assert_eq!(tail, [1, 2, 3]);
+}
+
+/// If the comparator panics, the deque still holds every element once, for
+/// points all through the sort, up to its last comparison.
+#[test]
+fn panic() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let mut original: Vec<String> = wrapped_strings(1000).into_iter().collect();
+    original.sort();
+    let total = std::cell::Cell::new(0);
+    sort_deque_by(&mut wrapped_strings(1000), |a, b| {
+        total.set(total.get() + 1);
+        a.cmp(b)
+    });
+    let total = total.get();
+    for fail_at in [10, total / 4, total / 2, total - 100, total] {
+        let mut deque = wrapped_strings(1000);
+        let count = std::cell::Cell::new(0);
+        catch_unwind(AssertUnwindSafe(|| {
+            sort_deque_by(&mut deque, |a, b| {
+                count.set(count.get() + 1);
+                if count.get() == fail_at {
+                    panic!("Expected panic: this is normal");
+                }
+                a.cmp(b)
+            })
+        }))
+        .err()
+        .unwrap();
+        let mut list: Vec<String> = deque.into_iter().collect();
+        list.sort();
+        assert_eq!(list, original);
+    }
+}
+
+/// Two runs, merged with either one in scratch space, and split between the
+/// slices at points before, inside and after each run. Keys repeat, to check
+/// stability.
+#[test]
+fn merge_across_join() {
+    let len = 300;
+    for &(first_len, head_len) in &[(100, 50), (100, 150), (200, 100), (200, 250)] {
+        let mut list: Vec<(usize, usize)> = keys(len, 7).zip(0..).collect();
+        list[..first_len].sort_by_key(|x| x.0);
+        list[first_len..].sort_by_key(|x| x.0);
+        let (mut head, mut tail) = (list[..head_len].to_vec(), list[head_len..].to_vec());
+        sort_slices_by(&mut head, &mut tail, |a, b| a.0.cmp(&b.0));
+        let mut expected = list;
+        expected.sort_by_key(|x| x.0);
+        assert!(head.iter().chain(&tail).eq(expected.iter()));
+    }
+}
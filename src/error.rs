@@ -0,0 +1,339 @@
+//! Errors that say more than the comparator's own.
+
+#[cfg(test)]
+mod tests;
+
+use crate::sort::sort_with;
+use crate::{no_values, ord_comparator, Comparator, ListAddress};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::alloc::Layout;
+use core::any::Any;
+use core::cell::RefCell;
+use core::cmp::Ordering;
+use core::convert::Infallible;
+use core::fmt;
+use core::ops::Range;
+
+/// What went wrong in a sort.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SortErrorKind<E> {
+    /// The comparator returned an error.
+    Comparator(E),
+    /// The comparator isn't a total order: a merge used up the run it had
+    /// moved to temporary storage before the other run, which its earlier
+    /// answers ruled out.
+    InconsistentComparator,
+    /// The comparator panicked, with this payload. Only `try_sort_by_catching`
+    /// catches panics.
+    Panicked(PanicPayload),
+    /// The sort was stopped before it finished, by running out of comparisons
+    /// or by being cancelled.
+    Cancelled,
+    /// The sort couldn't allocate temporary storage for a merge, or for the
+    /// stack of runs waiting to be merged.
+    OutOfMemory,
+}
+
+impl<E: fmt::Display> fmt::Display for SortErrorKind<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SortErrorKind::Comparator(e) => write!(f, "comparator failed: {}", e),
+            SortErrorKind::InconsistentComparator => {
+                f.write_str("comparison method violates its general contract")
+            }
+            SortErrorKind::Cancelled => f.write_str("sort cancelled"),
+            SortErrorKind::OutOfMemory => f.write_str("couldn't allocate memory for the sort"),
+            SortErrorKind::Panicked(payload) => match payload.message() {
+                Some(message) => write!(f, "comparator panicked: {}", message),
+                None => f.write_str("comparator panicked"),
+            },
+        }
+    }
+}
+
+/// What a comparator panicked with, as caught by `try_sort_by_catching`.
+///
+/// A panic payload can be sent to another thread but not shared with one.
+/// This is `Sync` anyway, so that a `SortError` can be passed up as a
+/// `Box<dyn Error + Send + Sync>`: the payload can only be taken out, never
+/// borrowed, and the message is copied out of it when the panic is caught.
+pub struct PanicPayload {
+    payload: Box<dyn Any + Send>,
+    message: Option<String>,
+}
+
+// SAFETY: a shared `PanicPayload` only gives access to `message`, never to
+// `payload`.
+unsafe impl Sync for PanicPayload {}
+
+impl PanicPayload {
+    #[cfg(feature = "std")]
+    fn new(payload: Box<dyn Any + Send>) -> Self {
+        let message = if let Some(message) = payload.downcast_ref::<&str>() {
+            Some(String::from(*message))
+        } else {
+            payload.downcast_ref::<String>().cloned()
+        };
+        PanicPayload { payload, message }
+    }
+
+    /// The panic's message, if the payload was a string, as it is for
+    /// `panic!`.
+    #[inline]
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// The payload itself, for example to carry on unwinding with
+    /// `std::panic::resume_unwind`.
+    #[inline]
+    pub fn into_inner(self) -> Box<dyn Any + Send> {
+        self.payload
+    }
+}
+
+impl fmt::Debug for PanicPayload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PanicPayload")
+            .field("message", &self.message)
+            .finish_non_exhaustive()
+    }
+}
+
+/// How far a sort got before it failed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SortProgress {
+    /// Runs found in the list so far.
+    pub runs_found: usize,
+    /// Merges finished.
+    pub merges: usize,
+    /// How much of the list run detection had got through: everything before
+    /// this was part of a run.
+    pub scanned: usize,
+    /// Where the two elements being compared were, if the comparator failed.
+    /// For a sort that was cancelled or ran out of comparisons, these are
+    /// the two elements it would have compared next. The positions are
+    /// `None` for an element that was in temporary storage in the middle of
+    /// a merge.
+    pub compared: Option<(Option<usize>, Option<usize>)>,
+    /// The parts of the list that are still sorted, in order. A failed merge
+    /// leaves the runs it was merging out of order, so they aren't included.
+    pub sorted_runs: Vec<Range<usize>>,
+}
+
+/// Why a sort failed, and how far it got.
+#[derive(Debug)]
+pub struct SortError<E> {
+    kind: SortErrorKind<E>,
+    progress: SortProgress,
+}
+
+impl<E> SortError<E> {
+    /// What went wrong.
+    #[inline]
+    pub fn kind(&self) -> &SortErrorKind<E> {
+        &self.kind
+    }
+
+    #[inline]
+    pub fn into_kind(self) -> SortErrorKind<E> {
+        self.kind
+    }
+
+    /// How far the sort got.
+    #[inline]
+    pub fn progress(&self) -> &SortProgress {
+        &self.progress
+    }
+
+    /// Turn the errors of a wrapped comparator into other kinds of error.
+    pub(crate) fn map_comparator<F>(self, f: impl FnOnce(E) -> SortErrorKind<F>) -> SortError<F> {
+        let kind = match self.kind {
+            SortErrorKind::Comparator(e) => f(e),
+            SortErrorKind::InconsistentComparator => SortErrorKind::InconsistentComparator,
+            SortErrorKind::Panicked(payload) => SortErrorKind::Panicked(payload),
+            SortErrorKind::Cancelled => SortErrorKind::Cancelled,
+            SortErrorKind::OutOfMemory => SortErrorKind::OutOfMemory,
+        };
+        SortError {
+            kind,
+            progress: self.progress,
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for SortError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}, after {} runs and {} merges",
+            self.kind, self.progress.runs_found, self.progress.merges
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug + fmt::Display> std::error::Error for SortError<E> {}
+
+/// A comparator that turns the merges' consistency checks into errors, and
+/// keeps track of the sort's progress for them.
+struct Checked<C> {
+    cmp: C,
+    /// Where the list is, to find where compared elements are.
+    address: ListAddress,
+    progress: RefCell<SortProgress>,
+}
+
+impl<C> Checked<C> {
+    fn new<T>(cmp: C, list: &[T]) -> Self {
+        Checked {
+            cmp,
+            address: ListAddress::of(list),
+            progress: RefCell::new(SortProgress::default()),
+        }
+    }
+
+    fn into_error<E>(self, kind: SortErrorKind<E>) -> SortError<E> {
+        SortError {
+            kind,
+            progress: self.progress.into_inner(),
+        }
+    }
+}
+
+impl<T, C: Comparator<T>> Comparator<T> for Checked<C> {
+    type Error = SortErrorKind<C::Error>;
+    #[inline(always)]
+    fn is_gt(&self, lhs: &T, rhs: &T) -> Result<bool, Self::Error> {
+        self.cmp.is_gt(lhs, rhs).map_err(|e| {
+            self.progress.borrow_mut().compared =
+                Some((self.address.index_of(lhs), self.address.index_of(rhs)));
+            SortErrorKind::Comparator(e)
+        })
+    }
+    fn run_found(&self, pos: usize, len: usize, _reversed: bool) {
+        let progress = &mut *self.progress.borrow_mut();
+        progress.runs_found += 1;
+        progress.scanned = pos + len;
+        progress.sorted_runs.push(pos..pos + len);
+    }
+    fn run_extended(&self, pos: usize, _len: usize, new_len: usize) {
+        let progress = &mut *self.progress.borrow_mut();
+        progress.scanned = pos + new_len;
+        match progress.sorted_runs.last_mut() {
+            Some(run) if run.start == pos => run.end = pos + new_len,
+            _ => progress.sorted_runs.push(pos..pos + new_len),
+        }
+    }
+    fn merge_start(&self, pos: usize, first_len: usize, _second_len: usize) {
+        // Until the merge is done, neither run is sorted.
+        let mid = pos + first_len;
+        let progress = &mut *self.progress.borrow_mut();
+        progress
+            .sorted_runs
+            .retain(|run| run.start != pos && run.start != mid);
+    }
+    fn merge_end(&self, pos: usize, len: usize) {
+        let progress = &mut *self.progress.borrow_mut();
+        progress.merges += 1;
+        let i = progress
+            .sorted_runs
+            .iter()
+            .position(|run| run.start > pos)
+            .unwrap_or(progress.sorted_runs.len());
+        progress.sorted_runs.insert(i, pos..pos + len);
+    }
+    fn inconsistent(&self) -> Result<(), Self::Error> {
+        Err(SortErrorKind::InconsistentComparator)
+    }
+    fn alloc_failed(&self, _layout: Layout) -> Self::Error {
+        SortErrorKind::OutOfMemory
+    }
+}
+
+/// Sorts the list like `try_sort_by`, but stops with
+/// `SortErrorKind::InconsistentComparator` if the comparator is caught
+/// contradicting itself, as Java's `TimSort` does.
+///
+/// Only one contradiction is checked for, the same one Java checks: before a
+/// merge, the runs are trimmed so that the run moved to temporary storage
+/// can't run out first, so if it does, the comparator's answers during the
+/// merge disagreed with its answers during the trimming. That check is free.
+/// Anything else, such as a comparator that answers differently each time
+/// but happens to stay in line with the trimming, isn't caught, and the list
+/// ends up in some unsorted order. `sort_by_audited` checks more thoroughly,
+/// at the cost of extra comparisons. Gallop results aren't checked, since a
+/// gallop can only ever return a position inside the run it searched,
+/// whatever the comparator says.
+///
+/// `try_sort_by` doesn't make this check, because its error type is the
+/// comparator's own, which has no way to say the comparator is inconsistent.
+///
+/// If the sort can't allocate its temporary storage, it stops with
+/// `SortErrorKind::OutOfMemory` instead of aborting.
+///
+/// Whatever the error, the list is left as a permutation of what it was, and
+/// the error says how far the sort got, including which parts of the list are
+/// still sorted.
+pub fn try_sort_by_checked<T, E, C: Fn(&T, &T) -> Result<Ordering, E>>(
+    list: &mut [T],
+    cmp: C,
+) -> Result<(), SortError<E>> {
+    let checked = Checked::new(ord_comparator(cmp), list);
+    sort_with(list, no_values(list.len()), &checked).map_err(|kind| checked.into_error(kind))
+}
+
+#[inline]
+pub fn sort_by_checked<T, C: Fn(&T, &T) -> Ordering>(
+    list: &mut [T],
+    cmp: C,
+) -> Result<(), SortError<Infallible>> {
+    try_sort_by_checked(list, move |a, b| -> Result<_, Infallible> { Ok(cmp(a, b)) })
+}
+
+/// What a comparison wrapped by `try_sort_by_catching` can fail with.
+#[cfg(feature = "std")]
+enum Caught<E> {
+    Error(E),
+    Panic(Box<dyn Any + Send>),
+}
+
+/// Sorts the list like `try_sort_by_checked`, but if the comparator panics,
+/// the panic is caught and returned as `SortErrorKind::Panicked` instead of
+/// unwinding out of the sort. The list is left as a permutation of what it
+/// was, as with any other comparator error.
+///
+/// The comparator isn't called again after it panics, but anything it shares
+/// with the caller, such as elements with interior mutability, may have been
+/// left half-updated.
+///
+/// Requires the `std` feature.
+#[cfg(feature = "std")]
+pub fn try_sort_by_catching<T, E, C: Fn(&T, &T) -> Result<Ordering, E>>(
+    list: &mut [T],
+    cmp: C,
+) -> Result<(), SortError<E>> {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    let catching = |a: &T, b: &T| match catch_unwind(AssertUnwindSafe(|| cmp(a, b))) {
+        Ok(ret) => ret.map_err(Caught::Error),
+        Err(payload) => Err(Caught::Panic(payload)),
+    };
+    try_sort_by_checked(list, catching).map_err(|err| {
+        err.map_comparator(|e| match e {
+            Caught::Error(e) => SortErrorKind::Comparator(e),
+            Caught::Panic(payload) => SortErrorKind::Panicked(PanicPayload::new(payload)),
+        })
+    })
+}
+
+#[cfg(feature = "std")]
+#[inline]
+pub fn sort_by_catching<T, C: Fn(&T, &T) -> Ordering>(
+    list: &mut [T],
+    cmp: C,
+) -> Result<(), SortError<Infallible>> {
+    try_sort_by_catching(list, move |a, b| -> Result<_, Infallible> { Ok(cmp(a, b)) })
+}
@@ -0,0 +1,210 @@
+//! Checked sorting.
+
+use super::{SortError, SortErrorKind};
+use crate::test_util::{fail_allocs_over, shuffled, xorshift};
+use crate::{sort_by_checked, try_sort_by_checked};
+use core::cmp::Ordering;
+use std::cell::Cell;
+
+/// A comparator that answers at random.
+fn random(seed: u64) -> impl Fn(&u32, &u32) -> Ordering {
+    let state = Cell::new(seed | 1);
+    move |_, _| {
+        let x = xorshift(state.get());
+        state.set(x);
+        match x % 3 {
+            0 => Ordering::Less,
+            1 => Ordering::Equal,
+            _ => Ordering::Greater,
+        }
+    }
+}
+
+#[test]
+fn consistent() {
+    let mut list = shuffled(1000);
+    assert!(sort_by_checked(&mut list, |a, b| a.cmp(b)).is_ok());
+    assert!(list.iter().enumerate().all(|(i, &x)| i as u32 == x));
+}
+
+#[test]
+fn comparator_error() {
+    let mut list: Vec<u32> = (0..1000).rev().collect();
+    let err = try_sort_by_checked(&mut list, |_, _| Err("fail")).unwrap_err();
+    assert!(matches!(err.kind(), SortErrorKind::Comparator("fail")));
+    // It failed on the very first comparison, in run detection.
+    assert_eq!(err.progress().compared, Some((Some(0), Some(1))));
+    assert_eq!(err.progress().runs_found, 0);
+    assert!(err.progress().sorted_runs.is_empty());
+}
+
+#[test]
+fn inconsistent() {
+    let mut caught = 0;
+    for seed in 0..100 {
+        let mut list: Vec<u32> = (0..1000).collect();
+        if let Err(e) = sort_by_checked(&mut list, random(seed)) {
+            assert!(matches!(e.kind(), SortErrorKind::InconsistentComparator));
+            assert_eq!(e.progress().compared, None);
+            caught += 1;
+        }
+        list.sort_unstable();
+        assert!(list.iter().enumerate().all(|(i, &x)| i as u32 == x));
+    }
+    // Not everything can be caught, but answering at random is hard to miss.
+    assert!(caught > 90);
+}
+
+/// Fail partway through, and check that the progress matches the list.
+#[test]
+fn progress() {
+    let original = shuffled(2000);
+    for fail_at in [100, 2000, 10_000, 18_000] {
+        let calls = Cell::new(0);
+        let mut list = original.clone();
+        let err = try_sort_by_checked(&mut list, |a, b| {
+            calls.set(calls.get() + 1);
+            if calls.get() == fail_at {
+                Err(())
+            } else {
+                Ok(a.cmp(b))
+            }
+        })
+        .unwrap_err();
+        let progress = err.progress();
+        assert!(progress.runs_found > progress.merges);
+        assert!(progress.scanned <= list.len());
+        let (left, right) = progress.compared.unwrap();
+        assert!(left.is_some() || right.is_some());
+        // The sorted runs are in order, don't overlap, and really are sorted.
+        for pair in progress.sorted_runs.windows(2) {
+            assert!(pair[0].end <= pair[1].start);
+        }
+        for run in &progress.sorted_runs {
+            assert!(run.end <= progress.scanned);
+            assert!(list[run.clone()].windows(2).all(|w| w[0] <= w[1]));
+        }
+        if fail_at == 18_000 {
+            assert!(progress.merges > 0);
+        }
+        let mut sorted = list.clone();
+        sorted.sort_unstable();
+        assert!(sorted.iter().enumerate().all(|(i, &x)| i as u32 == x));
+    }
+}
+
+/// A merge that can't allocate its temporary storage stops the sort, rather
+/// than aborting.
+#[test]
+fn out_of_memory() {
+    // Two long runs, which need a long merge.
+    let original: Vec<u64> = (0..500)
+        .map(|x| 2 * x)
+        .chain((0..500).map(|x| 2 * x + 1))
+        .collect();
+    let mut list = original.clone();
+    // Enough for short merges, but not long ones.
+    let err = fail_allocs_over(1000, || sort_by_checked(&mut list, |a, b| a.cmp(b))).unwrap_err();
+    assert!(matches!(err.kind(), SortErrorKind::OutOfMemory));
+    assert_eq!(err.progress().runs_found, 2);
+    assert_eq!(err.progress().compared, None);
+    // Nothing was moved.
+    assert_eq!(list, original);
+}
+
+#[test]
+fn display() {
+    let e: SortErrorKind<&str> = SortErrorKind::InconsistentComparator;
+    assert_eq!(
+        e.to_string(),
+        "comparison method violates its general contract"
+    );
+    assert_eq!(
+        SortErrorKind::Comparator("oops").to_string(),
+        "comparator failed: oops"
+    );
+    let mut list: Vec<u32> = (0..100).rev().collect();
+    let err = try_sort_by_checked(&mut list, |_, _| Err("oops")).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "comparator failed: oops, after 0 runs and 0 merges"
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn catching() {
+    use crate::{sort_by_catching, try_sort_by_catching};
+    let original = shuffled(1000);
+    for fail_at in [1, 100, 5000] {
+        let calls = Cell::new(0);
+        let mut list = original.clone();
+        let err = sort_by_catching(&mut list, |a, b| {
+            calls.set(calls.get() + 1);
+            if calls.get() == fail_at {
+                panic!("bad comparator");
+            }
+            a.cmp(b)
+        })
+        .unwrap_err();
+        assert_eq!(calls.get(), fail_at);
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "comparator panicked: bad comparator, after {} runs and {} merges",
+                err.progress().runs_found,
+                err.progress().merges
+            )
+        );
+        let mut sorted = list.clone();
+        sorted.sort_unstable();
+        assert!(sorted.iter().enumerate().all(|(i, &x)| i as u32 == x));
+    }
+
+    // Errors, and panics with other payloads, come through too.
+    let mut list = original.clone();
+    let err = try_sort_by_catching(&mut list, |_, _| Err("fail")).unwrap_err();
+    assert!(matches!(err.kind(), SortErrorKind::Comparator("fail")));
+    let err = try_sort_by_catching(&mut list, |_, _| -> Result<Ordering, ()> {
+        std::panic::panic_any(7_u8)
+    })
+    .unwrap_err();
+    match err.into_kind() {
+        SortErrorKind::Panicked(payload) => {
+            assert_eq!(payload.message(), None);
+            assert_eq!(payload.into_inner().downcast_ref(), Some(&7_u8));
+        }
+        kind => panic!("{:?}", kind),
+    }
+    assert!(sort_by_catching(&mut list, |a, b| a.cmp(b)).is_ok());
+}
+
+/// The crate's errors can all be passed up with `?` as a `std` error that
+/// can be sent between threads.
+#[cfg(feature = "std")]
+#[test]
+fn std_error() {
+    use crate::{sort_floats, try_sort_partial, NanPolicy, Trace};
+    use std::error::Error;
+    fn run(step: u32) -> Result<(), Box<dyn Error + Send + Sync>> {
+        match step {
+            0 => try_sort_by_checked(&mut [2, 1], |_, _| Err("oops"))?,
+            1 => try_sort_partial(&mut [1.0, f64::NAN])?,
+            2 => sort_floats(&mut [1.0, f64::NAN], NanPolicy::Error)?,
+            _ => drop("not a trace".parse::<Trace>()?),
+        }
+        Ok(())
+    }
+    for step in 0..4 {
+        let err = run(step).unwrap_err();
+        assert!(!err.to_string().is_empty());
+    }
+}
+
+/// Errors can be sent and shared between threads, including the payloads of
+/// caught panics, which on their own can't be shared.
+#[test]
+fn send_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SortError<&str>>();
+}
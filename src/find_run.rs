@@ -6,13 +6,19 @@ mod tests;
 
 use crate::Comparator;
 
-/// Find a run, reversing if necessary.
-pub(crate) fn get_run<T, C: Comparator<T>>(list: &mut [T], cmp: &C) -> Result<usize, C::Error> {
+/// Find a run, reversing if necessary. `values` is reversed along with the
+/// list. Returns the length of the run, and whether it was reversed.
+pub(crate) fn get_run<T, V, C: Comparator<T>>(
+    list: &mut [T],
+    values: &mut [V],
+    cmp: &C,
+) -> Result<(usize, bool), C::Error> {
     let (ord, len) = find_run(list, cmp)?;
     if ord {
         list[..len].reverse();
+        values[..len].reverse();
     }
-    Ok(len)
+    Ok((len, ord))
 }
 
 /// Find a run. Returns true if it needs reversed, and false otherwise.
@@ -20,27 +26,26 @@ pub(crate) fn find_run<T, C: Comparator<T>>(
     list: &[T],
     cmp: &C,
 ) -> Result<(bool, usize), C::Error> {
-    let (first, second) = match list {
-        [a, b, ..] => (a, b),
-        _ => return Ok((false, list.len())),
-    };
+    find_run_by(list.len(), |i| cmp.is_gt(&list[i], &list[i + 1]))
+}
+
+/// Find a run at the start of a list of `len` elements, where `is_gt(i)`
+/// says whether element `i` is greater than element `i + 1`. Returns true if
+/// it needs reversed, and the length of the run. The container sort finds
+/// its runs with this too.
+pub(crate) fn find_run_by<E>(
+    len: usize,
+    mut is_gt: impl FnMut(usize) -> Result<bool, E>,
+) -> Result<(bool, usize), E> {
+    if len < 2 {
+        return Ok((false, len));
+    }
+    // A run is strictly descending, or ascending with equal elements allowed,
+    // so that reversing it keeps the sort stable.
+    let gt = is_gt(0)?;
     let mut pos = 1;
-    let gt = if cmp.is_gt(first, second)? {
-        for pair in list[1..].windows(2) {
-            if !cmp.is_gt(&pair[0], &pair[1])? {
-                break;
-            }
-            pos += 1;
-        }
-        true
-    } else {
-        for pair in list[1..].windows(2) {
-            if cmp.is_gt(&pair[0], &pair[1])? {
-                break;
-            }
-            pos += 1;
-        }
-        false
-    };
+    while pos + 1 < len && is_gt(pos)? == gt {
+        pos += 1;
+    }
     Ok((gt, pos + 1))
 }
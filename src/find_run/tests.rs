@@ -1,24 +1,24 @@
-use crate::{comparator, never, ord_t_comparator};
+use crate::{comparator, never, no_values, ord_t_comparator};
 
 #[test]
 fn empty() {
     let list: Vec<usize> = vec![];
     let (ord, len) = find_run(&list);
-    assert_eq!(ord, false);
+    assert!(!ord);
     assert_eq!(len, 0);
 }
 
 #[test]
 fn single() {
     let (ord, len) = find_run(&[1]);
-    assert_eq!(ord, false);
+    assert!(!ord);
     assert_eq!(len, 1);
 }
 
 #[test]
 fn greater() {
     let (ord, len) = find_run(&[1, 2, 2, 3, 4, 5]);
-    assert_eq!(ord, false);
+    assert!(!ord);
     assert_eq!(len, 6);
 }
 
@@ -27,21 +27,21 @@ fn greater() {
 #[test]
 fn less_stable() {
     let (ord, len) = find_run(&[5, 4, 4, 3, 4, 5]);
-    assert_eq!(ord, true);
+    assert!(ord);
     assert_eq!(len, 2);
 }
 
 #[test]
 fn less() {
     let (ord, len) = find_run(&[5, 4, 3, 2, 1, 0]);
-    assert_eq!(ord, true);
+    assert!(ord);
     assert_eq!(len, 6);
 }
 
 #[test]
 fn equal() {
     let (ord, len) = find_run(&[2, 2, 2, 2, 2, 2]);
-    assert_eq!(ord, false);
+    assert!(!ord);
     assert_eq!(len, 6);
 }
 
@@ -76,5 +76,7 @@ fn find_run<T: Ord>(list: &[T]) -> (bool, usize) {
 
 /// With comparator.
 fn get_run<T: Ord>(list: &mut [T]) -> usize {
-    super::get_run(list, &ord_t_comparator()).unwrap_or_else(never)
+    super::get_run(list, no_values(list.len()), &ord_t_comparator())
+        .unwrap_or_else(never)
+        .0
 }
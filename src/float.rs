@@ -0,0 +1,175 @@
+//! Sorting for floating-point numbers, with an explicit choice of what to do
+//! about NaN and signed zeros instead of a hand-rolled comparator.
+
+#[cfg(test)]
+mod tests;
+
+use crate::sort_by;
+use core::cmp::Ordering;
+use core::fmt;
+
+/// How NaN is ordered.
+///
+/// `sort_floats` and `sort_floats_by_key` also tie the order of zeros to this:
+/// `-0.0` and `0.0` compare equal except under `TotalOrder`. To choose the two
+/// separately, use `sort_floats_by_key_with_zeros`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NanPolicy {
+    /// NaNs go before every other value. `-0.0` and `0.0` compare equal.
+    First,
+    /// NaNs go after every other value. `-0.0` and `0.0` compare equal.
+    Last,
+    /// Any NaN is an error, and the slice is left untouched. `-0.0` and `0.0`
+    /// compare equal.
+    Error,
+    /// The IEEE 754 `totalOrder` predicate, as implemented by `total_cmp`:
+    /// `-0.0` sorts before `0.0`, negative NaNs go first and positive NaNs go
+    /// last.
+    TotalOrder,
+}
+
+/// How `-0.0` and `0.0` are ordered.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ZeroPolicy {
+    /// The zeros compare equal, so they keep their original order.
+    Equal,
+    /// `-0.0` sorts before `0.0`.
+    NegativeFirst,
+}
+
+impl ZeroPolicy {
+    /// The order of zeros that goes with `policy` in `sort_floats`.
+    fn tied_to(policy: NanPolicy) -> Self {
+        match policy {
+            NanPolicy::TotalOrder => ZeroPolicy::NegativeFirst,
+            _ => ZeroPolicy::Equal,
+        }
+    }
+}
+
+/// The error returned by `NanPolicy::Error` when the input contains a NaN.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NanError {
+    /// The index of the first NaN in the slice.
+    pub index: usize,
+}
+
+impl fmt::Display for NanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NaN at index {}", self.index)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NanError {}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for f32 {}
+    impl Sealed for f64 {}
+}
+
+/// The floating-point types that can be sorted with a `NanPolicy`; that is,
+/// `f32` and `f64`.
+pub trait Float: Copy + PartialOrd + private::Sealed {
+    #[doc(hidden)]
+    fn is_nan(self) -> bool;
+    #[doc(hidden)]
+    fn is_sign_negative(self) -> bool;
+    #[doc(hidden)]
+    fn total_cmp(&self, other: &Self) -> Ordering;
+}
+
+impl Float for f32 {
+    #[inline]
+    fn is_nan(self) -> bool {
+        f32::is_nan(self)
+    }
+    #[inline]
+    fn is_sign_negative(self) -> bool {
+        f32::is_sign_negative(self)
+    }
+    #[inline]
+    fn total_cmp(&self, other: &Self) -> Ordering {
+        f32::total_cmp(self, other)
+    }
+}
+
+impl Float for f64 {
+    #[inline]
+    fn is_nan(self) -> bool {
+        f64::is_nan(self)
+    }
+    #[inline]
+    fn is_sign_negative(self) -> bool {
+        f64::is_sign_negative(self)
+    }
+    #[inline]
+    fn total_cmp(&self, other: &Self) -> Ordering {
+        f64::total_cmp(self, other)
+    }
+}
+
+/// Compare two floats according to the policies. `NanPolicy::Error` is
+/// checked up front, so here it behaves like any of the other non-total
+/// policies.
+#[inline]
+fn float_cmp<F: Float>(a: F, b: F, policy: NanPolicy, zeros: ZeroPolicy) -> Ordering {
+    if a.is_nan() || b.is_nan() {
+        if policy == NanPolicy::TotalOrder {
+            return a.total_cmp(&b);
+        }
+        let nan = match policy {
+            NanPolicy::First => Ordering::Less,
+            _ => Ordering::Greater,
+        };
+        return match (a.is_nan(), b.is_nan()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => nan,
+            _ => nan.reverse(),
+        };
+    }
+    match (a.partial_cmp(&b), zeros) {
+        // Equal values other than zeros have the same sign, so this only
+        // separates `-0.0` from `0.0`.
+        (Some(Ordering::Equal), ZeroPolicy::NegativeFirst) => {
+            b.is_sign_negative().cmp(&a.is_sign_negative())
+        }
+        // Neither is NaN, so they're comparable.
+        (ord, _) => ord.unwrap_or(Ordering::Equal),
+    }
+}
+
+/// Sorts a slice of floats, ordering NaN according to `policy`.
+#[inline]
+pub fn sort_floats<F: Float>(list: &mut [F], policy: NanPolicy) -> Result<(), NanError> {
+    sort_floats_by_key(list, |&x| x, policy)
+}
+
+/// Sorts the list by a floating-point key, ordering NaN keys according to
+/// `policy`. The key function is called on every comparison.
+#[inline]
+pub fn sort_floats_by_key<T, F: Float, K: Fn(&T) -> F>(
+    list: &mut [T],
+    key: K,
+    policy: NanPolicy,
+) -> Result<(), NanError> {
+    sort_floats_by_key_with_zeros(list, key, policy, ZeroPolicy::tied_to(policy))
+}
+
+/// Sorts the list by a floating-point key, ordering NaN keys according to
+/// `policy` and zero keys according to `zeros`.
+pub fn sort_floats_by_key_with_zeros<T, F: Float, K: Fn(&T) -> F>(
+    list: &mut [T],
+    key: K,
+    policy: NanPolicy,
+    zeros: ZeroPolicy,
+) -> Result<(), NanError> {
+    if policy == NanPolicy::Error {
+        if let Some(index) = list.iter().position(|x| key(x).is_nan()) {
+            return Err(NanError { index });
+        }
+    }
+    sort_by(list, |a, b| float_cmp(key(a), key(b), policy, zeros));
+    Ok(())
+}
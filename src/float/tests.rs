@@ -0,0 +1,106 @@
+use super::{
+    sort_floats, sort_floats_by_key, sort_floats_by_key_with_zeros, NanError, NanPolicy, ZeroPolicy,
+};
+
+/// Compare bit patterns, so NaNs and signed zeros are checked exactly.
+fn bits(list: &[f64]) -> Vec<u64> {
+    list.iter().map(|x| x.to_bits()).collect()
+}
+
+#[test]
+fn nan_first() {
+    let mut list = vec![3.0, f64::NAN, -1.0, 0.0, f64::NAN, -0.0];
+    sort_floats(&mut list, NanPolicy::First).unwrap();
+    assert!(list[0].is_nan() && list[1].is_nan());
+    // Zeros are equal, so they keep their original order.
+    assert_eq!(bits(&list[2..]), bits(&[-1.0, 0.0, -0.0, 3.0]));
+}
+
+#[test]
+fn nan_last() {
+    let mut list = vec![3.0f32, f32::NAN, -1.0, -0.0, 0.0];
+    sort_floats(&mut list, NanPolicy::Last).unwrap();
+    assert_eq!(&list[..4], &[-1.0, -0.0, 0.0, 3.0]);
+    assert!(list[4].is_nan());
+}
+
+#[test]
+fn nan_error() {
+    let mut list = vec![3.0, 1.0, f64::NAN, -1.0, f64::NAN];
+    let original = bits(&list);
+    assert_eq!(
+        sort_floats(&mut list, NanPolicy::Error),
+        Err(NanError { index: 2 })
+    );
+    assert_eq!(bits(&list), original);
+
+    let mut list = vec![3.0, 1.0, -1.0];
+    sort_floats(&mut list, NanPolicy::Error).unwrap();
+    assert_eq!(list, [-1.0, 1.0, 3.0]);
+}
+
+#[test]
+fn total_order() {
+    let neg_nan = -f64::NAN;
+    let mut list = vec![f64::NAN, 0.0, 2.0, -0.0, neg_nan, f64::NEG_INFINITY];
+    sort_floats(&mut list, NanPolicy::TotalOrder).unwrap();
+    assert_eq!(
+        bits(&list),
+        bits(&[neg_nan, f64::NEG_INFINITY, -0.0, 0.0, 2.0, f64::NAN])
+    );
+}
+
+#[test]
+fn by_key() {
+    #[derive(Debug, PartialEq)]
+    struct Row {
+        id: usize,
+        score: f32,
+    }
+    let mut list: Vec<Row> = (0..100)
+        .map(|id| Row {
+            id,
+            score: if id % 7 == 0 {
+                f32::NAN
+            } else {
+                (id % 10) as f32
+            },
+        })
+        .collect();
+    assert_eq!(
+        sort_floats_by_key(&mut list, |r| r.score, NanPolicy::Error),
+        Err(NanError { index: 0 })
+    );
+    sort_floats_by_key(&mut list, |r| r.score, NanPolicy::Last).unwrap();
+    let nans = list.iter().filter(|r| r.score.is_nan()).count();
+    assert_eq!(nans, 15);
+    for pair in list[..100 - nans].windows(2) {
+        assert!(pair[0].score <= pair[1].score);
+        if pair[0].score == pair[1].score {
+            assert!(pair[0].id < pair[1].id);
+        }
+    }
+    assert!(list[100 - nans..].iter().all(|r| r.score.is_nan()));
+}
+
+#[test]
+fn zeros_separate() {
+    let mut list = vec![0.0, f64::NAN, -0.0, 1.0, 0.0, -0.0];
+    sort_floats_by_key_with_zeros(
+        &mut list,
+        |&x| x,
+        NanPolicy::Last,
+        ZeroPolicy::NegativeFirst,
+    )
+    .unwrap();
+    assert_eq!(bits(&list[..5]), bits(&[-0.0, -0.0, 0.0, 0.0, 1.0]));
+    assert!(list[5].is_nan());
+
+    let mut list = vec![0.0, f64::NAN, -0.0, -f64::NAN];
+    sort_floats_by_key_with_zeros(&mut list, |&x| x, NanPolicy::TotalOrder, ZeroPolicy::Equal)
+        .unwrap();
+    assert!(list[0].is_nan() && list[0].is_sign_negative());
+    // Zeros are equal, so they keep their original order.
+    assert_eq!(bits(&list[1..3]), bits(&[0.0, -0.0]));
+    assert!(list[3].is_nan() && list[3].is_sign_positive());
+}
@@ -188,3 +188,14 @@ fn gallop_left<T: Ord>(key: &T, list: &[T], mode: Mode) -> usize {
 fn gallop_right<T: Ord>(key: &T, list: &[T], mode: Mode) -> usize {
     super::gallop_right(key, list, mode, &ord_t_comparator()).unwrap_or_else(never)
 }
+
+#[test]
+fn gallop_long_equal_cluster() {
+    let mut list = vec![0usize; 10];
+    list.extend(vec![1usize; 100]);
+    list.extend(vec![2usize; 10]);
+    test_both! {mode,
+        assert_eq!(gallop_left(&1, &list, mode), 10);
+        assert_eq!(gallop_right(&1, &list, mode), 110)
+    }
+}
@@ -6,26 +6,41 @@ mod tests;
 
 use crate::Comparator;
 
-/// Sorts the list using insertion sort.
+/// Sorts the list using insertion sort, moving `values` along with it.
 // This version was almost completely copied from libcollections/slice.rs
-pub(crate) fn sort<T, C: Comparator<T>>(list: &mut [T], cmp: &C) -> Result<(), C::Error> {
+pub(crate) fn sort<T, V, C: Comparator<T>>(
+    list: &mut [T],
+    values: &mut [V],
+    cmp: &C,
+) -> Result<(), C::Error> {
+    debug_assert_eq!(list.len(), values.len());
     if list.len() < 2 {
         return Ok(());
     }
     for i in 0..list.len() {
-        let i_el = &list[i];
-        // find the index just above the element that is in order wrt list[i]
-        let mut j = 0;
-        for (jj, j_el) in list[..i].iter().enumerate().rev() {
-            if !cmp.is_gt(j_el, i_el)? {
-                j = jj + 1;
-                break;
-            }
-        }
+        let j = insertion_point(i, |j| cmp.is_gt(&list[j], &list[i]))?;
         if i != j {
             // SAFETY: j<i, i<list.len
             unsafe { list.get_unchecked_mut(j..=i).rotate_right(1) };
+            values[j..=i].rotate_right(1);
         }
     }
     Ok(())
 }
+
+/// Where the element at `i` goes among the sorted elements before it: just
+/// after the last one that `is_gt(j)` says isn't greater than it, so equal
+/// elements stay in order. The scan goes backwards from `i`, which is quick
+/// for elements that are nearly in place already. The container sort inserts
+/// with this too.
+pub(crate) fn insertion_point<E>(
+    i: usize,
+    mut is_gt: impl FnMut(usize) -> Result<bool, E>,
+) -> Result<usize, E> {
+    for j in (0..i).rev() {
+        if !is_gt(j)? {
+            return Ok(j + 1);
+        }
+    }
+    Ok(0)
+}
@@ -1,4 +1,4 @@
-use crate::{comparator, never, ord_t_comparator};
+use crate::{comparator, never, no_values, ord_t_comparator};
 
 /// Test the insertion sort implementation with an empty list
 #[test]
@@ -68,7 +68,12 @@ fn stable() {
             Item { key1, key2 }
         })
         .collect();
-    super::sort(&mut list, &comparator(|a: &Item, b| Ok(a.key1 > b.key1))).unwrap_or_else(never);
+    super::sort(
+        &mut list,
+        no_values(len),
+        &comparator(|a: &Item, b| Ok(a.key1 > b.key1)),
+    )
+    .unwrap_or_else(never);
     for pair in list.windows(2) {
         let (a, b) = (&pair[0], &pair[1]);
         assert!(a.key1 <= b.key1);
@@ -80,5 +85,5 @@ fn stable() {
 
 /// Insertion sort implementation convenience used for tests.
 fn sort<T: Ord>(list: &mut [T]) {
-    super::sort(list, &ord_t_comparator()).unwrap_or_else(never);
+    super::sort(list, no_values(list.len()), &ord_t_comparator()).unwrap_or_else(never);
 }
@@ -3,19 +3,77 @@
 //! on an already-sorted list, smoothly becoming O(n log n) as the sorted
 //! sections (runs) get smaller and smaller.
 
-#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
 
 extern crate alloc;
 
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod cmp;
+
+mod audit;
+mod bytes;
+mod cancel;
+mod container;
+mod deque;
+mod error;
 mod find_run;
+mod float;
 mod gallop;
 mod insort;
 mod merge;
+mod observer;
+mod par;
+mod partial;
+mod permutation;
+mod progress;
 mod sort;
+mod stats;
+#[cfg(test)]
+mod test_util;
+mod trace;
 
+use alloc::alloc::handle_alloc_error;
+pub use audit::{sort_by_audited, AuditReport, Violation};
+pub use bytes::sort_bytes;
+pub use cancel::{
+    sort_by_cancellable, sort_by_with_budget, try_sort_by_cancellable, try_sort_by_with_budget,
+};
+pub use container::{sort_container, sort_container_with_scratch, ScratchContainer, SortContainer};
+use core::alloc::Layout;
 use core::cmp::Ordering;
 use core::convert::Infallible;
+use core::ptr::NonNull;
+pub use deque::{sort_deque, sort_deque_by, sort_slices_by};
+#[cfg(feature = "std")]
+pub use error::{sort_by_catching, try_sort_by_catching};
+pub use error::{
+    sort_by_checked, try_sort_by_checked, PanicPayload, SortError, SortErrorKind, SortProgress,
+};
+pub use float::{
+    sort_floats, sort_floats_by_key, sort_floats_by_key_with_zeros, Float, NanError, NanPolicy,
+    ZeroPolicy,
+};
+pub use observer::{sort_by_observed, try_sort_by_observed, SortObserver};
+#[cfg(feature = "std")]
+pub use par::{
+    par_merge_adjacent, par_merge_adjacent_by, par_merge_into, par_merge_into_by, par_sort,
+    par_sort_by, try_par_merge_adjacent_by, try_par_merge_into_by, try_par_sort_by, ThreadExecutor,
+};
+pub use par::{
+    try_par_merge_adjacent_by_in, try_par_merge_into_by_in, try_par_sort_by_in, Executor, Scope,
+};
+pub use partial::{try_sort_partial, try_sort_partial_by, Incomparable};
+pub use permutation::{
+    apply_permutation, argsort, argsort_by, invert_permutation, try_argsort_by,
+    try_sort_by_transactional,
+};
+pub use progress::{sort_by_with_progress, try_sort_by_with_progress, Progress};
 use sort::try_sort_by as try_sort_by_cmp;
+use sort::try_sort_by_reverse as try_sort_by_reverse_cmp;
+use sort::try_sort_zip_by as try_sort_zip_by_cmp;
+pub use stats::{sort_by_with_stats, try_sort_by_with_stats, SortStats};
+pub use trace::{ParseTraceError, Trace, TraceEvent};
 
 type NeverResult<T> = Result<T, Infallible>;
 #[inline(always)]
@@ -23,6 +81,43 @@ fn never<T>(x: Infallible) -> T {
     match x {}
 }
 
+/// The `values` to move along with a list that doesn't have any. Being
+/// zero-sized, every move made on them compiles away.
+#[inline(always)]
+fn no_values<'a>(len: usize) -> &'a mut [()] {
+    // SAFETY: `()` is zero-sized, so a dangling pointer is valid for any
+    // length, and there is no memory that could be aliased.
+    unsafe { core::slice::from_raw_parts_mut(NonNull::dangling().as_ptr(), len) }
+}
+
+/// Where a list is in memory, to tell which of its elements the comparator
+/// was passed. Elements in a merge's temporary storage aren't found.
+#[derive(Clone, Copy)]
+struct ListAddress {
+    base: usize,
+    len: usize,
+}
+
+impl ListAddress {
+    fn of<T>(list: &[T]) -> Self {
+        ListAddress {
+            base: list.as_ptr() as usize,
+            len: list.len(),
+        }
+    }
+
+    /// The index of `x` in the list, if it's there.
+    fn index_of<T>(self, x: &T) -> Option<usize> {
+        let size = core::mem::size_of::<T>();
+        let offset = (x as *const T as usize).wrapping_sub(self.base);
+        if size != 0 && offset < self.len * size {
+            Some(offset / size)
+        } else {
+            None
+        }
+    }
+}
+
 pub fn try_sort_by_gt<T, E, C: Fn(&T, &T) -> Result<bool, E>>(
     list: &mut [T],
     cmp: C,
@@ -54,18 +149,120 @@ pub fn sort<T: Ord>(list: &mut [T]) {
     sort_by(list, Ord::cmp)
 }
 
+/// Sorts `keys`, moving the elements of `values` the same way, so that each
+/// value stays next to its key. This is useful for struct-of-arrays layouts,
+/// where zipping the slices together would mean copying them.
+///
+/// # Panics
+///
+/// Panics if the slices have different lengths.
+#[inline]
+pub fn try_sort_zip_by<K, V, E, C: Fn(&K, &K) -> Result<Ordering, E>>(
+    keys: &mut [K],
+    values: &mut [V],
+    cmp: C,
+) -> Result<(), E> {
+    try_sort_zip_by_cmp(keys, values, ord_comparator(cmp))
+}
+
+#[inline]
+pub fn sort_zip_by<K, V, C: Fn(&K, &K) -> Ordering>(keys: &mut [K], values: &mut [V], cmp: C) {
+    try_sort_zip_by(keys, values, move |a, b| -> NeverResult<_> {
+        Ok(cmp(a, b))
+    })
+    .unwrap_or_else(never)
+}
+
+#[inline]
+pub fn sort_zip<K: Ord, V>(keys: &mut [K], values: &mut [V]) {
+    sort_zip_by(keys, values, Ord::cmp)
+}
+
+/// Like `try_sort_by`, but with CPython's `reverse` flag: when `reverse` is
+/// true the list ends up in descending order, and elements that compare equal
+/// keep their original relative order.
+#[inline]
+pub fn try_sort_by_reverse<T, E, C: Fn(&T, &T) -> Result<Ordering, E>>(
+    list: &mut [T],
+    cmp: C,
+    reverse: bool,
+) -> Result<(), E> {
+    try_sort_by_reverse_cmp(list, ord_comparator(cmp), reverse)
+}
+
+#[inline]
+pub fn sort_by_reverse<T, C: Fn(&T, &T) -> Ordering>(list: &mut [T], cmp: C, reverse: bool) {
+    try_sort_by_reverse(
+        list,
+        move |a, b| -> NeverResult<_> { Ok(cmp(a, b)) },
+        reverse,
+    )
+    .unwrap_or_else(never)
+}
+
+/// Sorts the list in descending order according to `cmp`, keeping equal
+/// elements in their original order.
+#[inline]
+pub fn sort_by_desc<T, C: Fn(&T, &T) -> Ordering>(list: &mut [T], cmp: C) {
+    sort_by_reverse(list, cmp, true)
+}
+
+#[inline]
+pub fn sort_desc<T: Ord>(list: &mut [T]) {
+    sort_by_desc(list, Ord::cmp)
+}
+
 trait Comparator<T> {
     type Error;
     fn is_gt(&self, lhs: &T, rhs: &T) -> Result<bool, Self::Error>;
-    fn ordering(&self, lhs: &T, rhs: &T) -> Result<Ordering, Self::Error> {
-        let ord = if self.is_gt(lhs, rhs)? {
-            Ordering::Greater
-        } else if self.is_gt(rhs, lhs)? {
-            Ordering::Less
-        } else {
-            Ordering::Equal
-        };
-        Ok(ord)
+
+    // Hooks called as the sort goes along, so a wrapping comparator can watch
+    // what it does. They do nothing by default, and compile away.
+
+    /// `find_run` found a run of `len` elements at `pos`. If `reversed`, it
+    /// was strictly descending and has been reversed.
+    #[inline(always)]
+    fn run_found(&self, _pos: usize, _len: usize, _reversed: bool) {}
+    /// The run at `pos` was lengthened from `len` to `new_len` elements with
+    /// insertion sort.
+    #[inline(always)]
+    fn run_extended(&self, _pos: usize, _len: usize, _new_len: usize) {}
+    /// The runs at `pos` of `first_len` elements and at `pos + first_len` of
+    /// `second_len` elements are about to be merged.
+    #[inline(always)]
+    fn merge_start(&self, _pos: usize, _first_len: usize, _second_len: usize) {}
+    /// The merge of `len` elements at `pos` is done.
+    #[inline(always)]
+    fn merge_end(&self, _pos: usize, _len: usize) {}
+    /// A merge moved `len` elements into temporary storage.
+    #[inline(always)]
+    fn scratch_used(&self, _len: usize) {}
+    /// A merge switched to galloping mode.
+    #[inline(always)]
+    fn gallop_enter(&self) {}
+    /// A merge left galloping mode, or finished while in it.
+    #[inline(always)]
+    fn gallop_exit(&self) {}
+    /// A merge moved `count` elements to their final place. Once the
+    /// comparator has failed or panicked, this isn't called again, even for
+    /// the elements a failed merge still puts back.
+    #[inline(always)]
+    fn moved(&self, _count: usize, _kind: merge::MoveKind) {}
+    /// A merge ran into something that can't happen if the comparator is a
+    /// total order. Return an error to stop the sort, or `Ok` to carry on
+    /// regardless; the result won't be sorted, but is still a permutation.
+    #[inline(always)]
+    fn inconsistent(&self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    /// The sort couldn't allocate temporary storage with this layout, either
+    /// for a merge or for the stack of runs waiting to be merged. Return the
+    /// error to stop the sort with; nothing has been moved since the last
+    /// merge finished, so the list is still a permutation. By default this
+    /// aborts, like any other allocation failure.
+    #[inline]
+    fn alloc_failed(&self, layout: Layout) -> Self::Error {
+        handle_alloc_error(layout)
     }
 }
 
@@ -100,9 +297,6 @@ pub(crate) fn ord_comparator<T, E, F: Fn(&T, &T) -> Result<Ordering, E>>(
         fn is_gt(&self, lhs: &T, rhs: &T) -> Result<bool, E> {
             (self.0)(lhs, rhs).map(|ord| ord == Ordering::Greater)
         }
-        fn ordering(&self, lhs: &T, rhs: &T) -> Result<Ordering, Self::Error> {
-            (self.0)(lhs, rhs)
-        }
     }
     OrdComparator(f)
 }
@@ -8,21 +8,25 @@ mod tests;
 use crate::gallop::{self, gallop_left, gallop_right};
 use crate::Comparator;
 use alloc::vec::Vec;
+use core::alloc::Layout;
 use core::mem::ManuallyDrop;
 use core::ptr;
 
-/// Merge implementation switch.
-pub(crate) fn merge<T, C: Comparator<T>>(
+/// Merge implementation switch. Every move made in `list` is made in `values`
+/// too, so they stay in lockstep.
+pub(crate) fn merge<T, V, C: Comparator<T>>(
     list: &mut [T],
+    values: &mut [V],
     mut first_len: usize,
     cmp: &C,
 ) -> Result<(), C::Error> {
+    debug_assert_eq!(list.len(), values.len());
     if first_len == 0 {
         return Ok(());
     }
     let (first, second) = list.split_at_mut(first_len);
     let second_len = gallop_left(first.last().unwrap(), second, gallop::Mode::Reverse, cmp)?;
-    let first_of_second = match second.get(0) {
+    let first_of_second = match second.first() {
         Some(x) => x,
         None => return Ok(()),
     };
@@ -33,24 +37,41 @@ pub(crate) fn merge<T, C: Comparator<T>>(
     }
 
     let nlist = &mut list[first_off..][..first_len + second_len];
+    let nvalues = &mut values[first_off..][..first_len + second_len];
     if first_len > second_len {
-        merge_hi(nlist, first_len, second_len, cmp)
+        merge_hi(nlist, nvalues, first_len, second_len, cmp)
     } else {
-        merge_lo(nlist, first_len, cmp)
+        merge_lo(nlist, nvalues, first_len, cmp)
     }
 }
 
+/// How a merge moved elements to their final place, for `Comparator::moved`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum MoveKind {
+    /// One at a time, after comparing with the head of the other run.
+    OneAtATime,
+    /// Several at once, after galloping to find how many.
+    Gallop,
+    /// All at once at the end, since the other run was used up.
+    Remainder,
+}
+
 /// The number of times any one run can win before we try galloping.
 /// Change this during testing.
-const MIN_GALLOP: usize = 7;
+pub(crate) const MIN_GALLOP: usize = 7;
 
 /// Merge implementation used when the first run is smaller than the second.
-pub(crate) fn merge_lo<T, C: Comparator<T>>(
+///
+/// The runs are expected to be trimmed as `merge` does, so that the last
+/// element of the first run belongs at the very end. If the first run runs out
+/// before the second, the comparator must be inconsistent.
+pub(crate) fn merge_lo<T, V, C: Comparator<T>>(
     list: &mut [T],
+    values: &mut [V],
     first_len: usize,
     cmp: &C,
 ) -> Result<(), C::Error> {
-    MergeLo::new(list, first_len, cmp).merge()
+    MergeLo::new(list, values, first_len, cmp)?.merge()
 }
 
 #[inline(always)]
@@ -59,295 +80,361 @@ fn md_as_inner<T>(x: &[ManuallyDrop<T>]) -> &[T] {
     unsafe { &*(x as *const [_] as *const [T]) }
 }
 
+/// One of the slices being merged, along with temporary storage for the run
+/// that was moved out of it. The keys are always one lane; the values of
+/// `sort_zip` are another.
+struct Lane<'a, T> {
+    list: &'a mut [T],
+    tmp: Vec<ManuallyDrop<T>>,
+}
+
+/// Allocate temporary storage for `len` elements. If that fails, `cmp`
+/// decides what error to stop the sort with.
+fn alloc_tmp<T, U, C: Comparator<T>>(
+    len: usize,
+    cmp: &C,
+) -> Result<Vec<ManuallyDrop<U>>, C::Error> {
+    let mut tmp = Vec::new();
+    match tmp.try_reserve_exact(len) {
+        Ok(()) => Ok(tmp),
+        // The elements are already in a slice, so this layout can't overflow.
+        Err(_) => Err(cmp.alloc_failed(Layout::array::<U>(len).unwrap())),
+    }
+}
+
+impl<'a, T> Lane<'a, T> {
+    /// Move `len` elements starting at `pos` into `tmp`, which must have room
+    /// for them, leaving the original contents uninitialized.
+    fn new(list: &'a mut [T], pos: usize, len: usize, mut tmp: Vec<ManuallyDrop<T>>) -> Self {
+        debug_assert!(tmp.is_empty() && tmp.capacity() >= len);
+        unsafe {
+            ptr::copy_nonoverlapping(
+                list.as_ptr().add(pos) as *const ManuallyDrop<T>,
+                tmp.as_mut_ptr(),
+                len,
+            );
+            tmp.set_len(len);
+        }
+        Lane { list, tmp }
+    }
+
+    /// Move `count` elements within the list. The ranges may overlap.
+    #[inline(always)]
+    unsafe fn copy(&mut self, src: usize, dest: usize, count: usize) {
+        let list = self.list.as_mut_ptr();
+        ptr::copy(list.add(src), list.add(dest), count);
+    }
+
+    /// Move `count` elements from temporary storage back into the list.
+    #[inline(always)]
+    unsafe fn copy_from_tmp(&mut self, src: usize, dest: usize, count: usize) {
+        ptr::copy_nonoverlapping(
+            self.tmp.as_ptr().add(src) as *const T,
+            self.list.as_mut_ptr().add(dest),
+            count,
+        );
+    }
+}
+
 /// Implementation of `merge_lo`. We need to have an object in order to
 /// implement panic safety.
-struct MergeLo<'a, T, C: Comparator<T>> {
+struct MergeLo<'a, T, V, C: Comparator<T>> {
     list_len: usize,
     first_pos: usize,
     first_len: usize,
     second_pos: usize,
     dest_pos: usize,
-    list: &'a mut [T],
-    tmp: Vec<ManuallyDrop<T>>,
+    keys: Lane<'a, T>,
+    values: Lane<'a, V>,
     cmp: &'a C,
 }
-impl<'a, T, C: Comparator<T>> MergeLo<'a, T, C> {
-    /// Constructor for a lower merge.
-    fn new(list: &'a mut [T], first_len: usize, cmp: &'a C) -> Self {
-        let mut ret_val = MergeLo {
+impl<'a, T, V, C: Comparator<T>> MergeLo<'a, T, V, C> {
+    /// Constructor for a lower merge. If the temporary storage can't be
+    /// allocated, nothing is moved.
+    fn new(
+        list: &'a mut [T],
+        values: &'a mut [V],
+        first_len: usize,
+        cmp: &'a C,
+    ) -> Result<Self, C::Error> {
+        let keys_tmp = alloc_tmp(first_len, cmp)?;
+        let values_tmp = alloc_tmp(first_len, cmp)?;
+        cmp.scratch_used(first_len);
+        Ok(MergeLo {
             list_len: list.len(),
             first_pos: 0,
             first_len,
             second_pos: first_len,
             dest_pos: 0,
-            list,
-            tmp: Vec::with_capacity(first_len),
+            // First, move the smallest run into temporary storage, leaving the
+            // original contents uninitialized.
+            keys: Lane::new(list, 0, first_len, keys_tmp),
+            values: Lane::new(values, 0, first_len, values_tmp),
             cmp,
-        };
-        // First, move the smallest run into temporary storage, leaving the
-        // original contents uninitialized.
-        unsafe {
-            ret_val.tmp.set_len(first_len);
-            ptr::copy_nonoverlapping(
-                ret_val.list.as_ptr() as *const ManuallyDrop<T>,
-                ret_val.tmp.as_mut_ptr(),
-                first_len,
-            );
-        }
-        ret_val
+        })
+    }
+    /// Move `count` elements from the start of the second run to the
+    /// destination.
+    #[inline(always)]
+    unsafe fn take_second(&mut self, count: usize) {
+        self.keys.copy(self.second_pos, self.dest_pos, count);
+        self.values.copy(self.second_pos, self.dest_pos, count);
+        self.second_pos += count;
+        self.dest_pos += count;
+    }
+    /// Move `count` elements from the start of the first run, in temporary
+    /// storage, to the destination.
+    #[inline(always)]
+    unsafe fn take_first(&mut self, count: usize) {
+        self.keys
+            .copy_from_tmp(self.first_pos, self.dest_pos, count);
+        self.values
+            .copy_from_tmp(self.first_pos, self.dest_pos, count);
+        self.first_pos += count;
+        self.dest_pos += count;
     }
     /// Perform the one-by-one comparison and insertion.
     fn merge(mut self) -> Result<(), C::Error> {
         let cmp = self.cmp;
         let mut first_count = 0;
         let mut second_count = 0;
+        let mut galloping = false;
         while self.second_pos > self.dest_pos && self.second_pos < self.list_len {
             debug_assert!(self.first_pos + (self.second_pos - self.first_len) == self.dest_pos);
             if (second_count | first_count) < MIN_GALLOP {
                 // One-at-a-time mode.
+                if galloping {
+                    galloping = false;
+                    cmp.gallop_exit();
+                }
                 unsafe {
                     if cmp.is_gt(
-                        self.tmp.get_unchecked(self.first_pos),
-                        self.list.get_unchecked(self.second_pos),
+                        self.keys.tmp.get_unchecked(self.first_pos),
+                        self.keys.list.get_unchecked(self.second_pos),
                     )? {
-                        ptr::copy_nonoverlapping(
-                            self.list.get_unchecked(self.second_pos),
-                            self.list.get_unchecked_mut(self.dest_pos),
-                            1,
-                        );
-                        self.second_pos += 1;
+                        self.take_second(1);
                         second_count += 1;
                         first_count = 0;
                     } else {
-                        ptr::copy_nonoverlapping(
-                            &**self.tmp.get_unchecked(self.first_pos),
-                            self.list.get_unchecked_mut(self.dest_pos),
-                            1,
-                        );
-                        self.first_pos += 1;
+                        self.take_first(1);
                         first_count += 1;
                         second_count = 0;
                     }
                 }
-                self.dest_pos += 1;
+                cmp.moved(1, MoveKind::OneAtATime);
             } else {
                 // Galloping mode.
+                if !galloping {
+                    galloping = true;
+                    cmp.gallop_enter();
+                }
                 second_count = gallop_left(
-                    unsafe { md_as_inner(&self.tmp).get_unchecked(self.first_pos) },
-                    &self.list[self.second_pos..],
+                    unsafe { md_as_inner(&self.keys.tmp).get_unchecked(self.first_pos) },
+                    &self.keys.list[self.second_pos..],
                     gallop::Mode::Forward,
                     cmp,
                 )?;
-                unsafe {
-                    ptr::copy(
-                        self.list.get_unchecked(self.second_pos),
-                        self.list.get_unchecked_mut(self.dest_pos),
-                        second_count,
-                    )
-                }
-                self.dest_pos += second_count;
-                self.second_pos += second_count;
+                unsafe { self.take_second(second_count) };
+                cmp.moved(second_count, MoveKind::Gallop);
                 debug_assert!(self.first_pos + (self.second_pos - self.first_len) == self.dest_pos);
                 if self.second_pos > self.dest_pos && self.second_pos < self.list_len {
                     first_count = gallop_right(
-                        unsafe { self.list.get_unchecked(self.second_pos) },
-                        md_as_inner(&self.tmp[self.first_pos..]),
+                        unsafe { self.keys.list.get_unchecked(self.second_pos) },
+                        md_as_inner(&self.keys.tmp[self.first_pos..]),
                         gallop::Mode::Forward,
                         cmp,
                     )?;
-                    unsafe {
-                        ptr::copy_nonoverlapping(
-                            md_as_inner(&self.tmp).get_unchecked(self.first_pos),
-                            self.list.get_unchecked_mut(self.dest_pos),
-                            first_count,
-                        )
-                    };
-                    self.dest_pos += first_count;
-                    self.first_pos += first_count;
+                    unsafe { self.take_first(first_count) };
+                    cmp.moved(first_count, MoveKind::Gallop);
                 }
             }
         }
+        if galloping {
+            cmp.gallop_exit();
+        }
+        if self.first_pos == self.first_len && self.second_pos < self.list_len {
+            cmp.inconsistent()?;
+        }
+        // The rest of the first run is moved into place when `self` is dropped.
+        let count = self.first_len - self.first_pos;
+        if count > 0 {
+            cmp.moved(count, MoveKind::Remainder);
+        }
         Ok(())
     }
 }
-impl<'a, T, C: Comparator<T>> Drop for MergeLo<'a, T, C> {
+impl<'a, T, V, C: Comparator<T>> Drop for MergeLo<'a, T, V, C> {
     /// Copy all remaining items in the temporary storage into the list.
     /// If the comparator panics, the result will not be sorted, but will still
     /// contain no duplicates or uninitialized spots.
     fn drop(&mut self) {
-        unsafe {
-            // Make sure that the entire tmp storage is consumed. Since there are no uninitialized
-            // spaces before dest_pos, and no uninitialized space after first_pos, this will ensure
-            // that there are no uninitialized spaces inside the slice after we drop. Thus, the
-            // function is safe.
-            if self.first_pos < self.first_len {
-                ptr::copy_nonoverlapping(
-                    self.tmp.get_unchecked(self.first_pos) as *const _ as *const T,
-                    self.list.get_unchecked_mut(self.dest_pos),
-                    self.first_len - self.first_pos,
-                );
-            }
-            // The temporary storage is now full of nothing but uninitialized.
-            // We want to deallocate the space, but not call the destructors.
-            self.tmp.set_len(0);
+        // Make sure that the entire tmp storage is consumed. Since there are no uninitialized
+        // spaces before dest_pos, and no uninitialized space after first_pos, this will ensure
+        // that there are no uninitialized spaces inside the slice after we drop. Thus, the
+        // function is safe. The temporary storage is `ManuallyDrop`, so freeing it afterwards
+        // won't call any destructors.
+        if self.first_pos < self.first_len {
+            let count = self.first_len - self.first_pos;
+            unsafe { self.take_first(count) };
         }
     }
 }
 
 /// Merge implementation used when the first run is larger than the second.
-pub(crate) fn merge_hi<T, C: Comparator<T>>(
+///
+/// The runs are expected to be trimmed as `merge` does, so that the first
+/// element of the second run belongs at the very start. If the second run runs
+/// out before the first, the comparator must be inconsistent.
+pub(crate) fn merge_hi<T, V, C: Comparator<T>>(
     list: &mut [T],
+    values: &mut [V],
     first_len: usize,
     second_len: usize,
     cmp: &C,
 ) -> Result<(), C::Error> {
-    MergeHi::new(list, first_len, second_len, cmp).merge()
+    MergeHi::new(list, values, first_len, second_len, cmp)?.merge()
 }
 
 /// Implementation of `merge_hi`. We need to have an object in order to
 /// implement panic safety.
-struct MergeHi<'a, T, C: Comparator<T>> {
+struct MergeHi<'a, T, V, C: Comparator<T>> {
     first_pos: isize,
     second_pos: isize,
     dest_pos: isize,
-    list: &'a mut [T],
-    tmp: Vec<ManuallyDrop<T>>,
+    keys: Lane<'a, T>,
+    values: Lane<'a, V>,
     cmp: &'a C,
 }
 
-impl<'a, T, C: Comparator<T>> MergeHi<'a, T, C> {
-    /// Constructor for a higher merge.
-    fn new(list: &'a mut [T], first_len: usize, second_len: usize, cmp: &'a C) -> Self {
-        let mut ret_val = MergeHi {
+impl<'a, T, V, C: Comparator<T>> MergeHi<'a, T, V, C> {
+    /// Constructor for a higher merge. If the temporary storage can't be
+    /// allocated, nothing is moved.
+    fn new(
+        list: &'a mut [T],
+        values: &'a mut [V],
+        first_len: usize,
+        second_len: usize,
+        cmp: &'a C,
+    ) -> Result<Self, C::Error> {
+        let keys_tmp = alloc_tmp(second_len, cmp)?;
+        let values_tmp = alloc_tmp(second_len, cmp)?;
+        cmp.scratch_used(second_len);
+        Ok(MergeHi {
             first_pos: first_len as isize - 1,
             second_pos: second_len as isize - 1,
             dest_pos: list.len() as isize - 1,
-            list,
-            tmp: Vec::with_capacity(second_len),
+            // First, move the smallest run into temporary storage, leaving the
+            // original contents uninitialized.
+            keys: Lane::new(list, first_len, second_len, keys_tmp),
+            values: Lane::new(values, first_len, second_len, values_tmp),
             cmp,
-        };
-        // First, move the smallest run into temporary storage, leaving the
-        // original contents uninitialized.
-        unsafe {
-            ret_val.tmp.set_len(second_len);
-            ptr::copy_nonoverlapping(
-                ret_val.list.as_ptr().add(first_len),
-                ret_val.tmp.as_mut_ptr() as *mut T,
-                second_len,
-            );
-        }
-        ret_val
+        })
+    }
+    /// Move `count` elements from the end of the first run to the
+    /// destination.
+    #[inline(always)]
+    unsafe fn take_first(&mut self, count: usize) {
+        let src = (self.first_pos + 1) as usize - count;
+        let dest = (self.dest_pos + 1) as usize - count;
+        self.keys.copy(src, dest, count);
+        self.values.copy(src, dest, count);
+        self.first_pos -= count as isize;
+        self.dest_pos -= count as isize;
+    }
+    /// Move `count` elements from the end of the second run, in temporary
+    /// storage, to the destination.
+    #[inline(always)]
+    unsafe fn take_second(&mut self, count: usize) {
+        let src = (self.second_pos + 1) as usize - count;
+        let dest = (self.dest_pos + 1) as usize - count;
+        self.keys.copy_from_tmp(src, dest, count);
+        self.values.copy_from_tmp(src, dest, count);
+        self.second_pos -= count as isize;
+        self.dest_pos -= count as isize;
     }
     /// Perform the one-by-one comparison and insertion.
     fn merge(mut self) -> Result<(), C::Error> {
         let cmp = self.cmp;
         let mut first_count: usize = 0;
         let mut second_count: usize = 0;
+        let mut galloping = false;
         while self.first_pos < self.dest_pos && self.first_pos >= 0 {
             debug_assert!(self.first_pos + self.second_pos + 1 == self.dest_pos);
             if (second_count | first_count) < MIN_GALLOP {
                 // One-at-a-time mode.
+                if galloping {
+                    galloping = false;
+                    cmp.gallop_exit();
+                }
                 unsafe {
                     if cmp.is_gt(
-                        self.list.get_unchecked(self.first_pos as usize),
-                        self.tmp.get_unchecked(self.second_pos as usize),
+                        self.keys.list.get_unchecked(self.first_pos as usize),
+                        self.keys.tmp.get_unchecked(self.second_pos as usize),
                     )? {
-                        ptr::copy_nonoverlapping(
-                            self.list.get_unchecked(self.first_pos as usize),
-                            self.list.get_unchecked_mut(self.dest_pos as usize),
-                            1,
-                        );
-                        self.first_pos -= 1;
+                        self.take_first(1);
                     } else {
-                        ptr::copy_nonoverlapping(
-                            md_as_inner(&self.tmp).get_unchecked(self.second_pos as usize),
-                            self.list.get_unchecked_mut(self.dest_pos as usize),
-                            1,
-                        );
-                        self.second_pos -= 1;
+                        self.take_second(1);
                     }
                 }
-                self.dest_pos -= 1;
+                cmp.moved(1, MoveKind::OneAtATime);
             } else {
                 // Galloping mode.
+                if !galloping {
+                    galloping = true;
+                    cmp.gallop_enter();
+                }
                 first_count = self.first_pos as usize + 1
                     - gallop_right(
-                        unsafe { md_as_inner(&self.tmp).get_unchecked(self.second_pos as usize) },
-                        &self.list[..=self.first_pos as usize],
+                        unsafe {
+                            md_as_inner(&self.keys.tmp).get_unchecked(self.second_pos as usize)
+                        },
+                        &self.keys.list[..=self.first_pos as usize],
                         gallop::Mode::Reverse,
                         cmp,
                     )?;
-                unsafe {
-                    copy_backwards(
-                        self.list.get_unchecked(self.first_pos as usize),
-                        self.list.get_unchecked_mut(self.dest_pos as usize),
-                        first_count,
-                    )
-                }
-                self.dest_pos -= first_count as isize;
-                self.first_pos -= first_count as isize;
+                unsafe { self.take_first(first_count) };
+                cmp.moved(first_count, MoveKind::Gallop);
                 debug_assert!(self.first_pos + self.second_pos + 1 == self.dest_pos);
                 if self.first_pos < self.dest_pos && self.first_pos >= 0 {
                     second_count = self.second_pos as usize + 1
                         - gallop_left(
-                            unsafe { self.list.get_unchecked(self.first_pos as usize) },
-                            md_as_inner(&self.tmp[..=self.second_pos as usize]),
+                            unsafe { self.keys.list.get_unchecked(self.first_pos as usize) },
+                            md_as_inner(&self.keys.tmp[..=self.second_pos as usize]),
                             gallop::Mode::Reverse,
                             cmp,
                         )?;
-                    unsafe {
-                        copy_nonoverlapping_backwards(
-                            md_as_inner(&self.tmp).get_unchecked(self.second_pos as usize),
-                            self.list.get_unchecked_mut(self.dest_pos as usize),
-                            second_count,
-                        )
-                    }
-                    self.dest_pos -= second_count as isize;
-                    self.second_pos -= second_count as isize;
+                    unsafe { self.take_second(second_count) };
+                    cmp.moved(second_count, MoveKind::Gallop);
                 }
             }
         }
+        if galloping {
+            cmp.gallop_exit();
+        }
+        if self.second_pos < 0 && self.first_pos >= 0 {
+            cmp.inconsistent()?;
+        }
+        // The rest of the second run is moved into place when `self` is
+        // dropped.
+        if self.second_pos >= 0 {
+            cmp.moved(self.second_pos as usize + 1, MoveKind::Remainder);
+        }
         Ok(())
     }
 }
 
-/// Perform a backwards `ptr::copy_nonoverlapping`. Behave identically when size = 1, but behave
-/// differently all other times
-unsafe fn copy_backwards<T>(src: *const T, dest: *mut T, size: usize) {
-    ptr::copy(
-        src.sub(size.wrapping_sub(1)),
-        dest.sub(size.wrapping_sub(1)),
-        size,
-    )
-}
-
-/// Perform a backwards `ptr::copy_nonoverlapping`. Behave identically when size = 1, but behave
-/// differently all other times
-unsafe fn copy_nonoverlapping_backwards<T>(src: *const T, dest: *mut T, size: usize) {
-    ptr::copy_nonoverlapping(
-        src.sub(size.wrapping_sub(1)),
-        dest.sub(size.wrapping_sub(1)),
-        size,
-    )
-}
-
-impl<'a, T, C: Comparator<T>> Drop for MergeHi<'a, T, C> {
+impl<'a, T, V, C: Comparator<T>> Drop for MergeHi<'a, T, V, C> {
     /// Copy all remaining items in the temporary storage into the list.
     /// If the comparator panics, the result will not be sorted, but will still
     /// contain no duplicates or uninitialized spots.
     fn drop(&mut self) {
-        unsafe {
-            // Make sure that the entire tmp storage is consumed. Since there are no uninitialized
-            // spaces before dest_pos, and no uninitialized space after first_pos, this will ensure
-            // that there are no uninitialized spaces inside the slice after we drop. Thus, the
-            // function is safe.
-            if self.second_pos >= 0 {
-                copy_nonoverlapping_backwards(
-                    md_as_inner(&self.tmp).get_unchecked(self.second_pos as usize),
-                    self.list.get_unchecked_mut(self.dest_pos as usize),
-                    self.second_pos as usize + 1,
-                );
-            }
+        // Make sure that the entire tmp storage is consumed. Since there are no uninitialized
+        // spaces before dest_pos, and no uninitialized space after first_pos, this will ensure
+        // that there are no uninitialized spaces inside the slice after we drop. Thus, the
+        // function is safe.
+        if self.second_pos >= 0 {
+            let count = self.second_pos as usize + 1;
+            unsafe { self.take_second(count) };
         }
     }
 }
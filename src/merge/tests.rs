@@ -2,7 +2,9 @@
 //! sized temporary slice of the same type. Naturally, it can only merge slices
 //! that are themselves already sorted.
 
-use crate::{comparator, never, ord_t_comparator};
+use super::MoveKind;
+use crate::{comparator, never, no_values, ord_t_comparator, Comparator};
+use std::cell::Cell;
 
 /// Test mergeing two empty slices.
 #[test]
@@ -88,6 +90,7 @@ fn lo_panic() {
     catch_unwind(AssertUnwindSafe(|| {
         super::merge(
             &mut list,
+            no_values(5),
             3,
             &comparator(|_, _| panic!("Expected panic: this is normal")),
         )
@@ -113,6 +116,7 @@ fn hi_panic() {
     catch_unwind(AssertUnwindSafe(|| {
         super::merge(
             &mut list,
+            no_values(5),
             2,
             &comparator(|_, _| panic!("Expected panic: this is normal")),
         )
@@ -242,7 +246,125 @@ fn hi_gallop_stress() {
     assert!(list[30] == 30);
 }
 
+/// Values follow their keys through both merge directions, including
+/// galloping mode.
+#[test]
+fn zip_gallop() {
+    for &first_len in &[10, 21] {
+        let mut list: Vec<u32> = (0..10).chain(0..21).map(|i| i * 2).collect();
+        list[..first_len].sort();
+        list[first_len..].sort();
+        let mut values: Vec<String> = list.iter().map(|k| k.to_string()).collect();
+        super::merge(&mut list, &mut values, first_len, &ord_t_comparator()).unwrap_or_else(never);
+        assert!(list.windows(2).all(|w| w[0] <= w[1]));
+        for (k, v) in list.iter().zip(&values) {
+            assert_eq!(k.to_string(), *v);
+        }
+    }
+}
+
+/// If the comparator panics, values are restored in lockstep with the keys.
+#[test]
+fn zip_panic() {
+    use std::cell::Cell;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let mut list: Vec<usize> = (0..20).chain(0..10).collect();
+    let mut values: Vec<Box<usize>> = list.iter().map(|&k| Box::new(k)).collect();
+    let count = Cell::new(0);
+    catch_unwind(AssertUnwindSafe(|| {
+        super::merge(
+            &mut list,
+            &mut values,
+            20,
+            &comparator(|a: &usize, b| {
+                count.set(count.get() + 1);
+                if count.get() > 12 {
+                    panic!("Expected panic: this is normal");
+                }
+                Ok(a > b)
+            }),
+        )
+        .unwrap_or_else(never)
+    }))
+    .err()
+    .unwrap();
+    for (k, v) in list.iter().zip(&values) {
+        assert_eq!(k, &**v);
+    }
+}
+
+/// Counts the elements `moved` reports, and fails after `fail_after`
+/// comparisons, with an error or a panic.
+struct Moves {
+    compares: Cell<usize>,
+    fail_after: usize,
+    panics: bool,
+    moved: Cell<usize>,
+    moved_after_failure: Cell<bool>,
+}
+
+impl Comparator<usize> for Moves {
+    type Error = ();
+    fn is_gt(&self, lhs: &usize, rhs: &usize) -> Result<bool, ()> {
+        self.compares.set(self.compares.get() + 1);
+        if self.compares.get() > self.fail_after {
+            if self.panics {
+                panic!("Expected panic: this is normal");
+            }
+            return Err(());
+        }
+        Ok(lhs > rhs)
+    }
+    fn moved(&self, count: usize, _kind: MoveKind) {
+        if self.compares.get() > self.fail_after {
+            self.moved_after_failure.set(true);
+        }
+        self.moved.set(self.moved.get() + count);
+    }
+}
+
+/// `moved` accounts for every element a merge puts in place, but isn't called
+/// once the comparator has failed, even though the merge still moves the
+/// rest of its temporary storage back into the list.
+#[test]
+fn moved_only_before_failure() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    // Odd numbers, then even ones, spread over the same range so that
+    // nothing is trimmed.
+    for &(first_len, second_len) in &[(10, 30), (30, 10)] {
+        let len = first_len + second_len;
+        let original: Vec<usize> = (0..first_len)
+            .map(|i| 2 * ((i + 1) * second_len / first_len) + 1)
+            .chain((0..second_len).map(|i| 2 * i))
+            .collect();
+        let mut expected = original.clone();
+        expected.sort_unstable();
+        for &(fail_after, panics) in &[(usize::MAX, false), (5, false), (5, true)] {
+            let mut list = original.clone();
+            let moves = Moves {
+                compares: Cell::new(0),
+                fail_after,
+                panics,
+                moved: Cell::new(0),
+                moved_after_failure: Cell::new(false),
+            };
+            let ret = catch_unwind(AssertUnwindSafe(|| {
+                super::merge(&mut list, no_values(len), first_len, &moves)
+            }));
+            assert_eq!(ret.is_err(), panics);
+            assert!(!moves.moved_after_failure.get());
+            if fail_after == usize::MAX {
+                assert_eq!(moves.moved.get(), len);
+            }
+            list.sort_unstable();
+            assert_eq!(list, expected);
+        }
+    }
+}
+
 /// Merge convenience used for tests.
 fn merge<T: Ord>(list: &mut [T], first_len: usize) {
-    super::merge(list, first_len, &ord_t_comparator()).unwrap_or_else(never)
+    super::merge(list, no_values(list.len()), first_len, &ord_t_comparator()).unwrap_or_else(never)
 }
@@ -0,0 +1,154 @@
+//! Watching a sort as it happens.
+
+#[cfg(test)]
+mod tests;
+
+use crate::sort::sort_with;
+use crate::{never, no_values, ord_comparator, Comparator, NeverResult};
+use core::cell::RefCell;
+use core::cmp::Ordering;
+use core::ops::Range;
+
+/// Callbacks for the steps a sort takes, passed to `sort_by_observed`.
+///
+/// Every method does nothing by default, so implementations only need the
+/// ones they're interested in. `()` is an observer that ignores everything.
+/// It sets `OBSERVES` to `false`, so sorting with it is the same as
+/// `try_sort_by`, and costs nothing. Other observers are kept in a `RefCell`
+/// during the sort, so every call costs a borrow check as well.
+///
+/// Positions are indices into the list being sorted. Lists too short to be
+/// worth merging are insertion sorted as a whole, which shows up as a single
+/// `on_insertion_extend` of an empty run at 0.
+///
+/// If the comparator fails or panics, the sort stops where it is, and no
+/// more methods are called. A merge that was under way then has an
+/// `on_merge_start` with no `on_merge_end`, and an `on_gallop_enter` with no
+/// `on_gallop_exit` if it was galloping.
+pub trait SortObserver {
+    /// Whether to call the methods at all. If this is `false`, the sort
+    /// skips the observer entirely.
+    const OBSERVES: bool = true;
+
+    /// A run of `len` elements was found at `pos`. If `reversed`, it was
+    /// strictly descending and has been reversed.
+    fn on_run_found(&mut self, pos: usize, len: usize, reversed: bool) {
+        let _ = (pos, len, reversed);
+    }
+
+    /// The run at `pos` was too short, and was lengthened from `len` to
+    /// `new_len` elements with insertion sort.
+    fn on_insertion_extend(&mut self, pos: usize, len: usize, new_len: usize) {
+        let _ = (pos, len, new_len);
+    }
+
+    /// The adjacent runs `run1` and `run2` are about to be merged.
+    fn on_merge_start(&mut self, run1: Range<usize>, run2: Range<usize>) {
+        let _ = (run1, run2);
+    }
+
+    /// A merge switched to galloping, because one run kept winning.
+    fn on_gallop_enter(&mut self) {}
+
+    /// A merge switched back to one element at a time, or finished while
+    /// galloping.
+    fn on_gallop_exit(&mut self) {}
+
+    /// The runs were merged, and `run` is now sorted.
+    fn on_merge_end(&mut self, run: Range<usize>) {
+        let _ = run;
+    }
+}
+
+impl SortObserver for () {
+    const OBSERVES: bool = false;
+}
+
+impl<O: SortObserver + ?Sized> SortObserver for &mut O {
+    const OBSERVES: bool = O::OBSERVES;
+    fn on_run_found(&mut self, pos: usize, len: usize, reversed: bool) {
+        (**self).on_run_found(pos, len, reversed)
+    }
+    fn on_insertion_extend(&mut self, pos: usize, len: usize, new_len: usize) {
+        (**self).on_insertion_extend(pos, len, new_len)
+    }
+    fn on_merge_start(&mut self, run1: Range<usize>, run2: Range<usize>) {
+        (**self).on_merge_start(run1, run2)
+    }
+    fn on_gallop_enter(&mut self) {
+        (**self).on_gallop_enter()
+    }
+    fn on_gallop_exit(&mut self) {
+        (**self).on_gallop_exit()
+    }
+    fn on_merge_end(&mut self, run: Range<usize>) {
+        (**self).on_merge_end(run)
+    }
+}
+
+/// A comparator that passes the sort's hooks on to an observer.
+struct Observed<C, O> {
+    cmp: C,
+    observer: RefCell<O>,
+}
+
+impl<T, C: Comparator<T>, O: SortObserver> Comparator<T> for Observed<C, O> {
+    type Error = C::Error;
+    #[inline(always)]
+    fn is_gt(&self, lhs: &T, rhs: &T) -> Result<bool, Self::Error> {
+        self.cmp.is_gt(lhs, rhs)
+    }
+    fn run_found(&self, pos: usize, len: usize, reversed: bool) {
+        self.observer.borrow_mut().on_run_found(pos, len, reversed)
+    }
+    fn run_extended(&self, pos: usize, len: usize, new_len: usize) {
+        self.observer
+            .borrow_mut()
+            .on_insertion_extend(pos, len, new_len)
+    }
+    fn merge_start(&self, pos: usize, first_len: usize, second_len: usize) {
+        let mid = pos + first_len;
+        self.observer
+            .borrow_mut()
+            .on_merge_start(pos..mid, mid..mid + second_len)
+    }
+    fn merge_end(&self, pos: usize, len: usize) {
+        self.observer.borrow_mut().on_merge_end(pos..pos + len)
+    }
+    fn gallop_enter(&self) {
+        self.observer.borrow_mut().on_gallop_enter()
+    }
+    fn gallop_exit(&self) {
+        self.observer.borrow_mut().on_gallop_exit()
+    }
+}
+
+/// Sorts the list like `try_sort_by`, telling `observer` about each step.
+pub fn try_sort_by_observed<T, E, C, O>(list: &mut [T], cmp: C, observer: O) -> Result<(), E>
+where
+    C: Fn(&T, &T) -> Result<Ordering, E>,
+    O: SortObserver,
+{
+    if !O::OBSERVES {
+        return sort_with(list, no_values(list.len()), &ord_comparator(cmp));
+    }
+    let observed = Observed {
+        cmp: ord_comparator(cmp),
+        observer: RefCell::new(observer),
+    };
+    sort_with(list, no_values(list.len()), &observed)
+}
+
+#[inline]
+pub fn sort_by_observed<T, C, O>(list: &mut [T], cmp: C, observer: O)
+where
+    C: Fn(&T, &T) -> Ordering,
+    O: SortObserver,
+{
+    try_sort_by_observed(
+        list,
+        move |a, b| -> NeverResult<_> { Ok(cmp(a, b)) },
+        observer,
+    )
+    .unwrap_or_else(never)
+}
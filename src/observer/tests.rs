@@ -0,0 +1,157 @@
+//! Observer hooks.
+
+use super::SortObserver;
+use crate::{sort_by_observed, try_sort_by_observed};
+use core::ops::Range;
+
+#[derive(Debug, PartialEq)]
+enum Event {
+    RunFound(usize, usize, bool),
+    InsertionExtend(usize, usize, usize),
+    MergeStart(Range<usize>, Range<usize>),
+    GallopEnter,
+    GallopExit,
+    MergeEnd(Range<usize>),
+}
+
+#[derive(Default)]
+struct Recorder(Vec<Event>);
+
+impl SortObserver for Recorder {
+    fn on_run_found(&mut self, pos: usize, len: usize, reversed: bool) {
+        self.0.push(Event::RunFound(pos, len, reversed));
+    }
+    fn on_insertion_extend(&mut self, pos: usize, len: usize, new_len: usize) {
+        self.0.push(Event::InsertionExtend(pos, len, new_len));
+    }
+    fn on_merge_start(&mut self, run1: Range<usize>, run2: Range<usize>) {
+        self.0.push(Event::MergeStart(run1, run2));
+    }
+    fn on_gallop_enter(&mut self) {
+        self.0.push(Event::GallopEnter);
+    }
+    fn on_gallop_exit(&mut self) {
+        self.0.push(Event::GallopExit);
+    }
+    fn on_merge_end(&mut self, run: Range<usize>) {
+        self.0.push(Event::MergeEnd(run));
+    }
+}
+
+#[test]
+fn two_runs() {
+    let mut list: Vec<u32> = (0..256).map(|x| x * 2).collect();
+    list.extend((0..256).rev().map(|x| x * 2 + 1));
+    let mut recorder = Recorder::default();
+    sort_by_observed(&mut list, |a, b| a.cmp(b), &mut recorder);
+    assert!(list.iter().enumerate().all(|(i, &x)| i as u32 == x));
+    assert_eq!(
+        recorder.0,
+        [
+            // The first odd number, 511, continues the run of evens.
+            Event::RunFound(0, 257, false),
+            Event::RunFound(257, 255, true),
+            Event::MergeStart(0..257, 257..512),
+            Event::MergeEnd(0..512),
+        ]
+    );
+}
+
+#[test]
+fn short_runs() {
+    let mut list: Vec<u32> = (0..100).map(|x| (x * 37) % 100).collect();
+    let mut recorder = Recorder::default();
+    sort_by_observed(&mut list, |a, b| a.cmp(b), &mut recorder);
+    assert!(list.iter().enumerate().all(|(i, &x)| i as u32 == x));
+    // 100 elements make runs of at least 50.
+    assert_eq!(recorder.0[0], Event::RunFound(0, 3, false));
+    assert_eq!(recorder.0[1], Event::InsertionExtend(0, 3, 50));
+    assert_eq!(recorder.0.last(), Some(&Event::MergeEnd(0..100)));
+}
+
+#[test]
+fn short_list() {
+    let mut list = vec![2, 3, 1];
+    let mut recorder = Recorder::default();
+    sort_by_observed(&mut list, |a, b| a.cmp(b), &mut recorder);
+    assert_eq!(list, [1, 2, 3]);
+    assert_eq!(recorder.0, [Event::InsertionExtend(0, 0, 3)]);
+}
+
+#[test]
+fn gallop_balanced() {
+    let mut list: Vec<u32> = (0..8)
+        .flat_map(|i| (0..100).map(move |x| i * 200 + x))
+        .collect();
+    list.extend((0..8).flat_map(|i| (0..100).map(move |x| i * 200 + 100 + x)));
+    let mut recorder = Recorder::default();
+    sort_by_observed(&mut list, |a, b| a.cmp(b), &mut recorder);
+    assert!(list.windows(2).all(|w| w[0] < w[1]));
+    let enters = recorder
+        .0
+        .iter()
+        .filter(|e| **e == Event::GallopEnter)
+        .count();
+    let exits = recorder
+        .0
+        .iter()
+        .filter(|e| **e == Event::GallopExit)
+        .count();
+    assert!(enters > 0);
+    assert_eq!(enters, exits);
+}
+
+#[test]
+fn no_op() {
+    let mut list: Vec<u32> = (0..1000).rev().collect();
+    assert_eq!(
+        try_sort_by_observed(&mut list, |a, b| -> Result<_, ()> { Ok(a.cmp(b)) }, ()),
+        Ok(())
+    );
+    assert!(list.iter().enumerate().all(|(i, &x)| i as u32 == x));
+}
+
+/// An observer that turns itself off isn't called.
+#[test]
+fn not_observing() {
+    struct Off;
+    impl SortObserver for Off {
+        const OBSERVES: bool = false;
+        fn on_run_found(&mut self, _pos: usize, _len: usize, _reversed: bool) {
+            panic!("called");
+        }
+    }
+    let mut list: Vec<u32> = (0..1000).rev().collect();
+    sort_by_observed(&mut list, |a, b| a.cmp(b), Off);
+    assert!(list.iter().enumerate().all(|(i, &x)| i as u32 == x));
+}
+
+/// A merge that fails starts but never ends.
+#[test]
+fn failed_merge() {
+    let mut list: Vec<u32> = (0..256).map(|x| x * 2).collect();
+    list.extend((0..256).map(|x| x * 2 + 1));
+    let mut recorder = Recorder::default();
+    let calls = std::cell::Cell::new(0);
+    let ret = try_sort_by_observed(
+        &mut list,
+        |a, b| {
+            calls.set(calls.get() + 1);
+            // Run detection takes 511 comparisons, and trimming a few more.
+            if calls.get() > 550 {
+                return Err(());
+            }
+            Ok(a.cmp(b))
+        },
+        &mut recorder,
+    );
+    assert_eq!(ret, Err(()));
+    assert_eq!(
+        recorder.0,
+        [
+            Event::RunFound(0, 256, false),
+            Event::RunFound(256, 256, false),
+            Event::MergeStart(0..256, 256..512),
+        ]
+    );
+}
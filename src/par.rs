@@ -0,0 +1,377 @@
+//! Parallel sorting. The list is cut into a piece for every thread, the
+//! pieces are sorted with the usual algorithm at the same time, and then
+//! they're merged back together, with the big merges split up between
+//! threads as well. The threads themselves come from an
+//! `Executor`.
+
+#[cfg(test)]
+mod tests;
+
+mod executor;
+
+use crate::gallop::{self, gallop_left, gallop_right};
+use crate::merge::merge;
+use crate::sort::sort_with;
+#[cfg(feature = "std")]
+use crate::{never, NeverResult};
+use crate::{no_values, ord_comparator, Comparator};
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+#[cfg(feature = "std")]
+pub use executor::ThreadExecutor;
+pub use executor::{Executor, Scope};
+
+/// Pieces shorter than this aren't worth handing to another thread, whether
+/// for sorting or merging.
+const MIN_PAR_LEN: usize = 1 << 12;
+
+/// Sort the list using up to `threads` threads. The list is cut into one
+/// piece per thread, the pieces are sorted in a scope at the same time, and
+/// then they're merged back together in pairs.
+fn sort_threads<T, C, X>(list: &mut [T], cmp: &C, exec: &X, threads: usize) -> Result<(), C::Error>
+where
+    X: Executor,
+    T: Send,
+    C: Comparator<T> + Sync,
+    C::Error: Send,
+{
+    let pieces = threads.min(list.len() / MIN_PAR_LEN);
+    if pieces <= 1 {
+        return sort_with(list, no_values(list.len()), cmp);
+    }
+    let piece_len = (list.len() + pieces - 1) / pieces;
+    let mut results = Vec::new();
+    results.resize_with((list.len() + piece_len - 1) / piece_len, || Ok(()));
+    exec.scope(|scope| {
+        for (piece, result) in list.chunks_mut(piece_len).zip(&mut results) {
+            scope.spawn(move || *result = sort_with(piece, no_values(piece.len()), cmp));
+        }
+    });
+    results.into_iter().collect::<Result<(), _>>()?;
+    merge_pieces(list, piece_len, cmp, exec, threads)
+}
+
+/// Merge the sorted pieces of `piece_len` elements (the last one may be
+/// shorter) that `sort_threads` cut the list into, using up to `threads`
+/// threads.
+fn merge_pieces<T, C, X>(
+    list: &mut [T],
+    piece_len: usize,
+    cmp: &C,
+    exec: &X,
+    threads: usize,
+) -> Result<(), C::Error>
+where
+    X: Executor,
+    T: Send,
+    C: Comparator<T> + Sync,
+    C::Error: Send,
+{
+    let pieces = (list.len() + piece_len - 1) / piece_len;
+    if pieces <= 1 {
+        return Ok(());
+    }
+    let mid = pieces / 2 * piece_len;
+    let (first, second) = list.split_at_mut(mid);
+    let (a, b) = exec.join(
+        || merge_pieces(first, piece_len, cmp, exec, threads / 2),
+        || merge_pieces(second, piece_len, cmp, exec, threads - threads / 2),
+    );
+    a?;
+    b?;
+    merge_threads(list, mid, cmp, exec, threads)
+}
+
+/// Find where to split the sorted runs `first` and `second` so that
+/// everything before the split points belongs before everything after them
+/// once merged.
+///
+/// The longer run is split at its middle element, and the other run is split
+/// where that element would go; this co-ranking is done with the same gallop
+/// searches that trim the runs of a normal merge. Equal elements from the
+/// first run always end up on the left of the split, so the merges on either
+/// side stay stable.
+fn split_runs<T, C: Comparator<T>>(
+    first: &[T],
+    second: &[T],
+    cmp: &C,
+) -> Result<(usize, usize), C::Error> {
+    if first.len() >= second.len() {
+        // Everything in the second run that's less than the pivot goes left.
+        let i = first.len() / 2;
+        let j = gallop_left(&first[i], second, gallop::Mode::Forward, cmp)?;
+        Ok((i, j))
+    } else {
+        // Everything in the first run that's no greater than the pivot goes
+        // left.
+        let j = second.len() / 2;
+        let i = gallop_right(&second[j], first, gallop::Mode::Forward, cmp)?;
+        Ok((i, j))
+    }
+}
+
+/// Merge the sorted runs `list[..mid]` and `list[mid..]` using up to
+/// `threads` threads. Rotating the middle pieces of the split past each other
+/// leaves two smaller merges that don't overlap, so they can run at the same
+/// time.
+fn merge_threads<T, C, X>(
+    list: &mut [T],
+    mid: usize,
+    cmp: &C,
+    exec: &X,
+    threads: usize,
+) -> Result<(), C::Error>
+where
+    X: Executor,
+    T: Send,
+    C: Comparator<T> + Sync,
+    C::Error: Send,
+{
+    if threads <= 1 || list.len() < 2 * MIN_PAR_LEN {
+        return merge(list, no_values(list.len()), mid, cmp);
+    }
+    let (first, second) = list.split_at(mid);
+    let (first_split, second_split) = split_runs(first, second, cmp)?;
+    list[first_split..mid + second_split].rotate_left(mid - first_split);
+    let (left, right) = list.split_at_mut(first_split + second_split);
+    let (a, b) = exec.join(
+        || merge_threads(left, first_split, cmp, exec, threads / 2),
+        || merge_threads(right, mid - first_split, cmp, exec, threads - threads / 2),
+    );
+    a?;
+    b
+}
+
+/// Merge the sorted runs `first` and `second` into `dest`, one element at a
+/// time.
+fn merge_into<T: Clone, C: Comparator<T>>(
+    first: &[T],
+    second: &[T],
+    dest: &mut [T],
+    cmp: &C,
+) -> Result<(), C::Error> {
+    let (mut i, mut j) = (0, 0);
+    for slot in dest {
+        if i < first.len() && (j == second.len() || !cmp.is_gt(&first[i], &second[j])?) {
+            slot.clone_from(&first[i]);
+            i += 1;
+        } else {
+            slot.clone_from(&second[j]);
+            j += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Merge the sorted runs `first` and `second` into `dest` using up to
+/// `threads` threads. Since nothing moves in the inputs, each split just cuts
+/// all three slices in two.
+fn merge_into_threads<T, C, X>(
+    first: &[T],
+    second: &[T],
+    dest: &mut [T],
+    cmp: &C,
+    exec: &X,
+    threads: usize,
+) -> Result<(), C::Error>
+where
+    X: Executor,
+    T: Clone + Send + Sync,
+    C: Comparator<T> + Sync,
+    C::Error: Send,
+{
+    if threads <= 1 || dest.len() < 2 * MIN_PAR_LEN {
+        return merge_into(first, second, dest, cmp);
+    }
+    let (first_split, second_split) = split_runs(first, second, cmp)?;
+    let (first_left, first_right) = first.split_at(first_split);
+    let (second_left, second_right) = second.split_at(second_split);
+    let (dest_left, dest_right) = dest.split_at_mut(first_split + second_split);
+    let (a, b) = exec.join(
+        || merge_into_threads(first_left, second_left, dest_left, cmp, exec, threads / 2),
+        || {
+            merge_into_threads(
+                first_right,
+                second_right,
+                dest_right,
+                cmp,
+                exec,
+                threads - threads / 2,
+            )
+        },
+    );
+    a?;
+    b
+}
+
+/// Sorts the list, splitting the work up with `exec`. The result is the same
+/// as `try_sort_by`; if the comparator fails in more than one piece of work,
+/// one of the errors is returned.
+pub fn try_par_sort_by_in<T, E, C, X>(exec: &X, list: &mut [T], cmp: C) -> Result<(), E>
+where
+    T: Send,
+    E: Send,
+    C: Fn(&T, &T) -> Result<Ordering, E> + Sync,
+    X: Executor,
+{
+    sort_threads(list, &ord_comparator(cmp), exec, exec.parallelism())
+}
+
+/// Sorts the list using all of the available cores, like `try_par_sort_by_in`
+/// with a `ThreadExecutor`.
+///
+/// Requires the `std` feature.
+#[cfg(feature = "std")]
+#[inline]
+pub fn try_par_sort_by<T, E, C>(list: &mut [T], cmp: C) -> Result<(), E>
+where
+    T: Send,
+    E: Send,
+    C: Fn(&T, &T) -> Result<Ordering, E> + Sync,
+{
+    try_par_sort_by_in(&ThreadExecutor, list, cmp)
+}
+
+#[cfg(feature = "std")]
+#[inline]
+pub fn par_sort_by<T, C>(list: &mut [T], cmp: C)
+where
+    T: Send,
+    C: Fn(&T, &T) -> Ordering + Sync,
+{
+    try_par_sort_by(list, move |a, b| -> NeverResult<_> { Ok(cmp(a, b)) }).unwrap_or_else(never)
+}
+
+#[cfg(feature = "std")]
+#[inline]
+pub fn par_sort<T: Ord + Send>(list: &mut [T]) {
+    par_sort_by(list, Ord::cmp)
+}
+
+/// Merges the sorted runs `list[..mid]` and `list[mid..]` in place, splitting
+/// the work up with `exec`. Like the merges inside a sort, this is stable: if
+/// elements compare equal, the ones from the first run come first.
+///
+/// If the comparator fails, the list is left in an unspecified order.
+///
+/// # Panics
+///
+/// Panics if `mid > list.len()`.
+pub fn try_par_merge_adjacent_by_in<T, E, C, X>(
+    exec: &X,
+    list: &mut [T],
+    mid: usize,
+    cmp: C,
+) -> Result<(), E>
+where
+    T: Send,
+    E: Send,
+    C: Fn(&T, &T) -> Result<Ordering, E> + Sync,
+    X: Executor,
+{
+    assert!(mid <= list.len(), "merge point out of range");
+    merge_threads(list, mid, &ord_comparator(cmp), exec, exec.parallelism())
+}
+
+/// Merges the sorted runs `list[..mid]` and `list[mid..]` in place using all
+/// of the available cores, like `try_par_merge_adjacent_by_in` with a
+/// `ThreadExecutor`.
+///
+/// Requires the `std` feature.
+#[cfg(feature = "std")]
+#[inline]
+pub fn try_par_merge_adjacent_by<T, E, C>(list: &mut [T], mid: usize, cmp: C) -> Result<(), E>
+where
+    T: Send,
+    E: Send,
+    C: Fn(&T, &T) -> Result<Ordering, E> + Sync,
+{
+    try_par_merge_adjacent_by_in(&ThreadExecutor, list, mid, cmp)
+}
+
+#[cfg(feature = "std")]
+#[inline]
+pub fn par_merge_adjacent_by<T, C>(list: &mut [T], mid: usize, cmp: C)
+where
+    T: Send,
+    C: Fn(&T, &T) -> Ordering + Sync,
+{
+    try_par_merge_adjacent_by(list, mid, move |a, b| -> NeverResult<_> { Ok(cmp(a, b)) })
+        .unwrap_or_else(never)
+}
+
+#[cfg(feature = "std")]
+#[inline]
+pub fn par_merge_adjacent<T: Ord + Send>(list: &mut [T], mid: usize) {
+    par_merge_adjacent_by(list, mid, Ord::cmp)
+}
+
+/// Merges the sorted runs `first` and `second` into `dest`, splitting the work
+/// up with `exec`, and without moving anything in the inputs. The merge is
+/// stable, as with `try_par_merge_adjacent_by_in`.
+///
+/// If the comparator fails, `dest` is left partly overwritten.
+///
+/// # Panics
+///
+/// Panics if `dest.len() != first.len() + second.len()`.
+pub fn try_par_merge_into_by_in<T, E, C, X>(
+    exec: &X,
+    first: &[T],
+    second: &[T],
+    dest: &mut [T],
+    cmp: C,
+) -> Result<(), E>
+where
+    T: Clone + Send + Sync,
+    E: Send,
+    C: Fn(&T, &T) -> Result<Ordering, E> + Sync,
+    X: Executor,
+{
+    assert_eq!(
+        first.len() + second.len(),
+        dest.len(),
+        "destination length mismatch"
+    );
+    let cmp = ord_comparator(cmp);
+    merge_into_threads(first, second, dest, &cmp, exec, exec.parallelism())
+}
+
+/// Merges the sorted runs `first` and `second` into `dest` using all of the
+/// available cores, like `try_par_merge_into_by_in` with a `ThreadExecutor`.
+///
+/// Requires the `std` feature.
+#[cfg(feature = "std")]
+#[inline]
+pub fn try_par_merge_into_by<T, E, C>(
+    first: &[T],
+    second: &[T],
+    dest: &mut [T],
+    cmp: C,
+) -> Result<(), E>
+where
+    T: Clone + Send + Sync,
+    E: Send,
+    C: Fn(&T, &T) -> Result<Ordering, E> + Sync,
+{
+    try_par_merge_into_by_in(&ThreadExecutor, first, second, dest, cmp)
+}
+
+#[cfg(feature = "std")]
+#[inline]
+pub fn par_merge_into_by<T, C>(first: &[T], second: &[T], dest: &mut [T], cmp: C)
+where
+    T: Clone + Send + Sync,
+    C: Fn(&T, &T) -> Ordering + Sync,
+{
+    try_par_merge_into_by(first, second, dest, move |a, b| -> NeverResult<_> {
+        Ok(cmp(a, b))
+    })
+    .unwrap_or_else(never)
+}
+
+#[cfg(feature = "std")]
+#[inline]
+pub fn par_merge_into<T: Ord + Clone + Send + Sync>(first: &[T], second: &[T], dest: &mut [T]) {
+    par_merge_into_by(first, second, dest, Ord::cmp)
+}
@@ -0,0 +1,153 @@
+//! How the parallel sorts get their work run.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// Runs pieces of a parallel sort at the same time.
+///
+/// The sorts need two kinds of parallelism. Sorting fans out: the list is
+/// cut into one piece per thread, and the pieces are sorted in a `scope`.
+/// Merging is fork-join: every split produces two halves that can run
+/// independently, and both have to finish before the results are combined,
+/// which is what `join` is for.
+///
+/// Only `join` and `parallelism` have to be implemented. By default, `scope`
+/// runs the tasks spawned in it through `join` once the closure passed to it
+/// returns. A pool that can start tasks straight away, as `ThreadExecutor`
+/// does with `std::thread::scope`, can override it to do that. Adapting
+/// rayon, for example:
+///
+/// ```ignore
+/// struct Rayon;
+///
+/// impl timsort::Executor for Rayon {
+///     fn join<A, B, RA, RB>(&self, a: A, b: B) -> (RA, RB)
+///     where
+///         A: FnOnce() -> RA + Send,
+///         B: FnOnce() -> RB + Send,
+///         RA: Send,
+///         RB: Send,
+///     {
+///         rayon::join(a, b)
+///     }
+///
+///     fn scope<'scope, F, R>(&self, f: F) -> R
+///     where
+///         F: for<'a> FnOnce(&timsort::Scope<'a, 'scope>) -> R,
+///     {
+///         rayon::in_place_scope(|s| f(&timsort::Scope::new(&|task| s.spawn(|_| task()))))
+///     }
+///
+///     fn parallelism(&self) -> usize {
+///         rayon::current_num_threads()
+///     }
+/// }
+/// ```
+pub trait Executor: Sync {
+    /// Runs `a` and `b`, possibly at the same time, and returns both results.
+    /// If either one panics, the panic should be passed on, but only once
+    /// both have finished.
+    fn join<A, B, RA, RB>(&self, a: A, b: B) -> (RA, RB)
+    where
+        A: FnOnce() -> RA + Send,
+        B: FnOnce() -> RB + Send,
+        RA: Send,
+        RB: Send;
+
+    /// Calls `f` with a `Scope` to spawn tasks in, which can borrow anything
+    /// that lives for `'scope`, and returns what `f` returns once every task
+    /// has finished. The tasks may run at the same time as each other and
+    /// as `f`. If any of them panics, the panic should be passed on, but
+    /// only once all of them have finished.
+    fn scope<'scope, F, R>(&self, f: F) -> R
+    where
+        F: for<'a> FnOnce(&Scope<'a, 'scope>) -> R,
+    {
+        let tasks = RefCell::new(Vec::new());
+        let ret = f(&Scope::new(&|task| tasks.borrow_mut().push(task)));
+        run_all(self, tasks.into_inner());
+        ret
+    }
+
+    /// How many pieces of work it's worth splitting a sort into, usually the
+    /// number of threads. With 1, nothing is split, and neither `join` nor
+    /// `scope` is called.
+    fn parallelism(&self) -> usize;
+}
+
+/// Where `Executor::scope` spawns tasks.
+pub struct Scope<'a, 'scope> {
+    spawn: &'a dyn Fn(Box<dyn FnOnce() + Send + 'scope>),
+}
+
+impl<'a, 'scope> Scope<'a, 'scope> {
+    /// A scope that starts tasks by passing them to `spawn`, for
+    /// implementing `Executor::scope`. `spawn` can run a task straight away,
+    /// hand it to another thread or keep it for later, as long as it has
+    /// finished by the time `scope` returns.
+    #[inline]
+    pub fn new(spawn: &'a dyn Fn(Box<dyn FnOnce() + Send + 'scope>)) -> Self {
+        Scope { spawn }
+    }
+
+    /// Start `task`, which may run at the same time as the code that spawned
+    /// it.
+    #[inline]
+    pub fn spawn(&self, task: impl FnOnce() + Send + 'scope) {
+        (self.spawn)(Box::new(task))
+    }
+}
+
+/// Run `tasks`, splitting them in half with `join` until there's one left.
+fn run_all<X: Executor + ?Sized>(exec: &X, mut tasks: Vec<Box<dyn FnOnce() + Send + '_>>) {
+    if tasks.len() > 1 {
+        let rest = tasks.split_off(tasks.len() / 2);
+        exec.join(|| run_all(exec, tasks), || run_all(exec, rest));
+    } else if let Some(task) = tasks.pop() {
+        task();
+    }
+}
+
+/// An executor that spawns a scoped thread for every `join` and every task
+/// in a `scope`, and splits the work into one piece per core.
+///
+/// Requires the `std` feature.
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ThreadExecutor;
+
+#[cfg(feature = "std")]
+impl Executor for ThreadExecutor {
+    fn join<A, B, RA, RB>(&self, a: A, b: B) -> (RA, RB)
+    where
+        A: FnOnce() -> RA + Send,
+        B: FnOnce() -> RB + Send,
+        RA: Send,
+        RB: Send,
+    {
+        std::thread::scope(|s| {
+            let b = s.spawn(b);
+            let ra = a();
+            match b.join() {
+                Ok(rb) => (ra, rb),
+                Err(payload) => std::panic::resume_unwind(payload),
+            }
+        })
+    }
+
+    fn scope<'scope, F, R>(&self, f: F) -> R
+    where
+        F: for<'a> FnOnce(&Scope<'a, 'scope>) -> R,
+    {
+        std::thread::scope(|s| {
+            f(&Scope::new(&|task| {
+                s.spawn(task);
+            }))
+        })
+    }
+
+    fn parallelism(&self) -> usize {
+        std::thread::available_parallelism().map_or(1, |n| n.get())
+    }
+}
@@ -0,0 +1,247 @@
+//! Parallel sorting and merging.
+
+use super::{merge_into_threads, merge_threads, sort_threads, Executor, MIN_PAR_LEN};
+use crate::test_util::xorshift;
+use crate::{ord_comparator, ord_t_comparator};
+use core::cmp::Ordering;
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::thread;
+
+/// Spawns a thread for every `join`, and counts them.
+#[derive(Default)]
+struct Counting {
+    joins: AtomicUsize,
+}
+
+impl Executor for Counting {
+    fn join<A, B, RA, RB>(&self, a: A, b: B) -> (RA, RB)
+    where
+        A: FnOnce() -> RA + Send,
+        B: FnOnce() -> RB + Send,
+        RA: Send,
+        RB: Send,
+    {
+        self.joins.fetch_add(1, AtomicOrdering::Relaxed);
+        thread::scope(|s| {
+            let b = s.spawn(b);
+            let ra = a();
+            match b.join() {
+                Ok(rb) => (ra, rb),
+                Err(payload) => panic::resume_unwind(payload),
+            }
+        })
+    }
+
+    fn parallelism(&self) -> usize {
+        4
+    }
+}
+
+/// A pseudo-random list with lots of duplicate keys, tagged with the original
+/// positions so stability can be checked.
+fn tagged(len: usize, keys: u64) -> Vec<(u64, usize)> {
+    let seed = Cell::new(0x2545_f491_4f6c_dd1du64);
+    (0..len)
+        .map(|i| {
+            let x = xorshift(seed.get());
+            seed.set(x);
+            (x % keys, i)
+        })
+        .collect()
+}
+
+fn by_key(a: &(u64, usize), b: &(u64, usize)) -> Result<Ordering, ()> {
+    Ok(a.0.cmp(&b.0))
+}
+
+#[test]
+fn sort_stable() {
+    for &threads in &[1, 2, 3, 8] {
+        let mut list = tagged(MIN_PAR_LEN * 10 + 17, 100);
+        let mut expected = list.clone();
+        expected.sort_by_key(|x| x.0);
+        sort_threads(
+            &mut list,
+            &ord_comparator(by_key),
+            &Counting::default(),
+            threads,
+        )
+        .unwrap();
+        assert_eq!(list, expected);
+    }
+}
+
+#[test]
+fn sort_small() {
+    let mut list = tagged(100, 10);
+    let mut expected = list.clone();
+    expected.sort_by_key(|x| x.0);
+    sort_threads(&mut list, &ord_comparator(by_key), &Counting::default(), 8).unwrap();
+    assert_eq!(list, expected);
+}
+
+#[test]
+fn merge_uneven() {
+    // Lopsided runs, so the pivot is taken from either side.
+    for &first_len in &[1, MIN_PAR_LEN, MIN_PAR_LEN * 7] {
+        let mut list = tagged(MIN_PAR_LEN * 8, 50);
+        list[..first_len].sort_by_key(|x| x.0);
+        list[first_len..].sort_by_key(|x| x.0);
+        let mut expected = list.clone();
+        expected.sort_by_key(|x| x.0);
+        merge_threads(
+            &mut list,
+            first_len,
+            &ord_comparator(by_key),
+            &Counting::default(),
+            8,
+        )
+        .unwrap();
+        assert_eq!(list, expected);
+    }
+}
+
+#[test]
+fn sort_error() {
+    let calls = AtomicUsize::new(0);
+    let mut list: Vec<u32> = (0..MIN_PAR_LEN as u32 * 8).rev().collect();
+    let cmp = ord_comparator(|a: &u32, b: &u32| {
+        if calls.fetch_add(1, AtomicOrdering::Relaxed) == 1000 {
+            Err("fail")
+        } else {
+            Ok(a.cmp(b))
+        }
+    });
+    assert_eq!(
+        sort_threads(&mut list, &cmp, &Counting::default(), 4),
+        Err("fail")
+    );
+    list.sort_unstable();
+    assert!(list.iter().enumerate().all(|(i, &x)| i as u32 == x));
+}
+
+#[test]
+fn sort_panic() {
+    let calls = AtomicUsize::new(0);
+    let mut list = tagged(MIN_PAR_LEN * 8, 100);
+    let cmp = ord_comparator(|a: &(u64, usize), b: &(u64, usize)| {
+        if calls.fetch_add(1, AtomicOrdering::Relaxed) == 50_000 {
+            panic!("comparator");
+        }
+        by_key(a, b)
+    });
+    let ret = panic::catch_unwind(AssertUnwindSafe(|| {
+        sort_threads(&mut list, &cmp, &Counting::default(), 4)
+    }));
+    assert!(ret.is_err());
+    list.sort_unstable_by_key(|x| x.1);
+    assert!(list.iter().enumerate().all(|(i, x)| i == x.1));
+}
+
+#[test]
+fn sort_ord() {
+    let mut list: Vec<u32> = (0..MIN_PAR_LEN as u32 * 4)
+        .map(|x| x.wrapping_mul(2_654_435_761))
+        .collect();
+    let mut expected = list.clone();
+    expected.sort();
+    sort_threads(&mut list, &ord_t_comparator(), &Counting::default(), 4).unwrap();
+    assert_eq!(list, expected);
+}
+
+#[test]
+fn merge_into_uneven() {
+    for &first_len in &[0, 1, MIN_PAR_LEN, MIN_PAR_LEN * 7] {
+        let mut list = tagged(MIN_PAR_LEN * 8, 50);
+        list[..first_len].sort_by_key(|x| x.0);
+        list[first_len..].sort_by_key(|x| x.0);
+        let (first, second) = list.split_at(first_len);
+        let mut dest = vec![(0, 0); list.len()];
+        merge_into_threads(
+            first,
+            second,
+            &mut dest,
+            &ord_comparator(by_key),
+            &Counting::default(),
+            8,
+        )
+        .unwrap();
+        list.sort_by_key(|x| x.0);
+        assert_eq!(dest, list);
+    }
+}
+
+#[test]
+fn executor() {
+    let exec = Counting::default();
+    let mut list: Vec<u32> = (0..MIN_PAR_LEN as u32 * 8).rev().collect();
+    crate::try_par_sort_by_in(&exec, &mut list, |a, b| -> Result<_, ()> { Ok(a.cmp(b)) }).unwrap();
+    assert!(list.iter().enumerate().all(|(i, &x)| i as u32 == x));
+    // The four pieces are sorted in a scope that runs them with three joins,
+    // and then merged back together with three more.
+    assert!(exec.joins.load(AtomicOrdering::Relaxed) >= 6);
+}
+
+#[test]
+fn scope() {
+    fn run<X: Executor>(exec: &X) {
+        let mut list = vec![0; 100];
+        let ret = exec.scope(|scope| {
+            for (i, x) in list.iter_mut().enumerate() {
+                scope.spawn(move || *x = i);
+            }
+            "done"
+        });
+        assert_eq!(ret, "done");
+        assert!(list.iter().enumerate().all(|(i, &x)| i == x));
+    }
+    let exec = Counting::default();
+    run(&exec);
+    assert_eq!(exec.joins.load(AtomicOrdering::Relaxed), 99);
+    #[cfg(feature = "std")]
+    run(&super::ThreadExecutor);
+}
+
+#[test]
+fn scope_panic() {
+    let exec = Counting::default();
+    let finished = AtomicUsize::new(0);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        exec.scope(|scope| {
+            for i in 0..8 {
+                let finished = &finished;
+                scope.spawn(move || {
+                    if i == 3 {
+                        panic!("task {}", i);
+                    }
+                    finished.fetch_add(1, AtomicOrdering::Relaxed);
+                });
+            }
+        })
+    }));
+    assert!(result.is_err());
+    assert_eq!(finished.load(AtomicOrdering::Relaxed), 7);
+}
+
+#[test]
+fn merge_public() {
+    let exec = Counting::default();
+    let mut list: Vec<u32> = (0..MIN_PAR_LEN as u32 * 4).map(|x| x * 2).collect();
+    list.extend((0..MIN_PAR_LEN as u32 * 4).map(|x| x * 2 + 1));
+    let mid = list.len() / 2;
+    let mut dest = vec![0; list.len()];
+    let cmp = |a: &u32, b: &u32| -> Result<_, ()> { Ok(a.cmp(b)) };
+    crate::try_par_merge_into_by_in(&exec, &list[..mid], &list[mid..], &mut dest, cmp).unwrap();
+    crate::try_par_merge_adjacent_by_in(&exec, &mut list, mid, cmp).unwrap();
+    assert!(list.iter().enumerate().all(|(i, &x)| i as u32 == x));
+    assert_eq!(dest, list);
+}
+
+#[test]
+#[should_panic(expected = "destination length mismatch")]
+fn merge_into_length_mismatch() {
+    let cmp = |a: &u32, b: &u32| -> Result<_, ()> { Ok(a.cmp(b)) };
+    let _ = crate::try_par_merge_into_by_in(&Counting::default(), &[1, 2], &[3], &mut [0; 4], cmp);
+}
@@ -0,0 +1,66 @@
+//! Sorting for `PartialOrd` types. Instead of panicking or making something
+//! up when two elements can't be compared, the sort stops and reports them.
+
+#[cfg(test)]
+mod tests;
+
+use crate::{ord_comparator, try_sort_by_cmp, ListAddress};
+use core::cmp::Ordering;
+use core::fmt;
+
+/// The error returned when the comparator finds a pair of elements that
+/// compare as `None`, such as a NaN.
+///
+/// The slice is left in an unspecified order, but it still contains every
+/// element exactly once. Elements that were in the slice when they were
+/// compared haven't moved since, so `list[i]` is the offending element.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Incomparable {
+    /// Where the left-hand side of the failed comparison is, or `None` if it
+    /// was in temporary storage in the middle of a merge.
+    pub left: Option<usize>,
+    /// Where the right-hand side is, in the same way.
+    pub right: Option<usize>,
+}
+
+impl fmt::Display for Incomparable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn side(f: &mut fmt::Formatter<'_>, index: Option<usize>) -> fmt::Result {
+            match index {
+                Some(i) => write!(f, "element {}", i),
+                None => f.write_str("an element being merged"),
+            }
+        }
+        side(f, self.left)?;
+        f.write_str(" and ")?;
+        side(f, self.right)?;
+        f.write_str(" are not comparable")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Incomparable {}
+
+/// Sorts the list using a partial comparison function, failing with where
+/// the offending pair is if it ever returns `None`.
+pub fn try_sort_partial_by<T, F: Fn(&T, &T) -> Option<Ordering>>(
+    list: &mut [T],
+    cmp: F,
+) -> Result<(), Incomparable> {
+    let address = ListAddress::of(list);
+    try_sort_by_cmp(
+        list,
+        ord_comparator(|a: &T, b: &T| {
+            cmp(a, b).ok_or_else(|| Incomparable {
+                left: address.index_of(a),
+                right: address.index_of(b),
+            })
+        }),
+    )
+}
+
+/// Sorts the list using `PartialOrd::partial_cmp`.
+#[inline]
+pub fn try_sort_partial<T: PartialOrd>(list: &mut [T]) -> Result<(), Incomparable> {
+    try_sort_partial_by(list, PartialOrd::partial_cmp)
+}
@@ -0,0 +1,70 @@
+use super::{try_sort_partial, try_sort_partial_by, Incomparable};
+
+#[test]
+fn floats() {
+    let mut list = vec![2.5, -1.0, 0.0, 7.25, -3.5];
+    assert_eq!(try_sort_partial(&mut list), Ok(()));
+    assert_eq!(list, [-3.5, -1.0, 0.0, 2.5, 7.25]);
+}
+
+/// A single NaN never gets compared, so it's not an error.
+#[test]
+fn single_nan() {
+    let mut list = vec![f64::NAN];
+    assert_eq!(try_sort_partial(&mut list), Ok(()));
+}
+
+#[test]
+fn nan() {
+    let mut list = vec![1.0, 2.0, f64::NAN, 0.5];
+    let err = try_sort_partial(&mut list).unwrap_err();
+    // A short list is sorted in place, so both sides are in the list.
+    let (left, right) = (err.left.unwrap(), err.right.unwrap());
+    assert!(list[left].is_nan() || list[right].is_nan());
+    // Still a permutation of the input.
+    assert_eq!(list.iter().filter(|x| x.is_nan()).count(), 1);
+    let mut rest: Vec<f64> = list.into_iter().filter(|x| !x.is_nan()).collect();
+    try_sort_partial(&mut rest).unwrap();
+    assert_eq!(rest, [0.5, 1.0, 2.0]);
+}
+
+/// Errors are reported from run detection in a long list; on this reversed
+/// input, the descending run reaches 250 before any merge.
+#[test]
+fn incomparable_long() {
+    let mut list: Vec<i32> = (0..500).rev().collect();
+    let err = try_sort_partial_by(&mut list, |a, b| {
+        if *a == 250 || *b == 250 {
+            None
+        } else {
+            a.partial_cmp(b)
+        }
+    })
+    .unwrap_err();
+    let (left, right) = (err.left.unwrap(), err.right.unwrap());
+    assert!(list[left] == 250 || list[right] == 250);
+    list.sort_unstable();
+    assert!(list.into_iter().eq(0..500));
+}
+
+#[test]
+fn display() {
+    let err = Incomparable {
+        left: Some(3),
+        right: None,
+    };
+    assert_eq!(
+        err.to_string(),
+        "element 3 and an element being merged are not comparable"
+    );
+}
+
+/// Elements don't need to be `Clone`.
+#[test]
+fn not_clone() {
+    #[derive(PartialEq, PartialOrd)]
+    struct Score(f32);
+    let mut list: Vec<Score> = [3.0, 1.0, 2.0].iter().map(|&x| Score(x)).collect();
+    assert_eq!(try_sort_partial(&mut list), Ok(()));
+    assert!(list.iter().map(|s| s.0).eq([1.0, 2.0, 3.0]));
+}
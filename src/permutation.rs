@@ -0,0 +1,144 @@
+//! Sorting permutations: the order the elements would be in, without moving
+//! the elements themselves, and applying that order afterwards.
+
+#[cfg(test)]
+mod tests;
+
+use crate::{never, try_sort_by, NeverResult};
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+/// Returns the permutation that would stably sort `list` according to `cmp`.
+/// That is, `list[perm[0]]`, `list[perm[1]]`, ... is in sorted order.
+///
+/// The indices are sorted with the same algorithm, so sorted runs in `list`
+/// are detected and merged like they would be for `try_sort_by`.
+pub fn try_argsort_by<T, E, C: Fn(&T, &T) -> Result<Ordering, E>>(
+    list: &[T],
+    cmp: C,
+) -> Result<Vec<usize>, E> {
+    let mut perm: Vec<usize> = (0..list.len()).collect();
+    try_sort_by(&mut perm, |&a, &b| cmp(&list[a], &list[b]))?;
+    Ok(perm)
+}
+
+#[inline]
+pub fn argsort_by<T, C: Fn(&T, &T) -> Ordering>(list: &[T], cmp: C) -> Vec<usize> {
+    try_argsort_by(list, move |a, b| -> NeverResult<_> { Ok(cmp(a, b)) }).unwrap_or_else(never)
+}
+
+#[inline]
+pub fn argsort<T: Ord>(list: &[T]) -> Vec<usize> {
+    argsort_by(list, Ord::cmp)
+}
+
+/// The top bit of an index. Slices of `usize` can't be longer than
+/// `isize::MAX`, so it's never set in a valid index, and we use it to mark
+/// indices we've already visited.
+const MARK: usize = !(usize::MAX >> 1);
+
+/// Panic unless `perm` contains every index from `0` to `perm.len()` once.
+/// The slice is left as it was.
+fn check_permutation(perm: &mut [usize]) {
+    let len = perm.len();
+    assert!(
+        perm.iter().all(|&i| i < len),
+        "permutation index out of range"
+    );
+    let mut unique = true;
+    for i in 0..len {
+        let j = perm[i] & !MARK;
+        if perm[j] & MARK != 0 {
+            unique = false;
+            break;
+        }
+        perm[j] |= MARK;
+    }
+    clear_marks(perm);
+    assert!(unique, "permutation contains a duplicate index");
+}
+
+fn clear_marks(perm: &mut [usize]) {
+    for i in perm {
+        *i &= !MARK;
+    }
+}
+
+/// Reorders `list` so that `list[i]` becomes the element that was at
+/// `list[perm[i]]`, as returned by `argsort`. This can be applied to any number
+/// of slices of the same length to sort them the same way.
+///
+/// The cycles of the permutation are followed in place, so the only extra
+/// memory is the top bit of each index in `perm`, which is used to mark
+/// visited elements. `perm` is restored before returning.
+///
+/// # Panics
+///
+/// Panics if the slices have different lengths, or `perm` is not a
+/// permutation of `0..perm.len()`. Nothing is moved in that case.
+pub fn apply_permutation<T>(list: &mut [T], perm: &mut [usize]) {
+    assert_eq!(list.len(), perm.len(), "permutation length mismatch");
+    check_permutation(perm);
+    for start in 0..perm.len() {
+        if perm[start] & MARK != 0 {
+            continue;
+        }
+        // Pull each element of the cycle into place, carrying the element
+        // that started at `start` along until it reaches its slot.
+        let mut cur = start;
+        loop {
+            let next = perm[cur];
+            perm[cur] |= MARK;
+            if next == start {
+                break;
+            }
+            list.swap(cur, next);
+            cur = next;
+        }
+    }
+    clear_marks(perm);
+}
+
+/// Sorts the list like `try_sort_by`, but if the comparator fails, the list is
+/// left exactly as it was, so the sort can be retried. The same goes if the
+/// comparator panics.
+///
+/// This works by sorting a buffer of indices, which is only applied to the
+/// list once the sort has succeeded, so it needs memory for one `usize` per
+/// element and moves every element an extra time.
+pub fn try_sort_by_transactional<T, E, C: Fn(&T, &T) -> Result<Ordering, E>>(
+    list: &mut [T],
+    cmp: C,
+) -> Result<(), E> {
+    let mut perm = try_argsort_by(list, cmp)?;
+    apply_permutation(list, &mut perm);
+    Ok(())
+}
+
+/// Inverts a permutation in place, so that if `perm[i] == j` before, then
+/// `perm[j] == i` after. Inverting the result of `argsort` gives the rank of
+/// every element, that is, where it ends up in the sorted order.
+///
+/// # Panics
+///
+/// Panics if `perm` is not a permutation of `0..perm.len()`, leaving it
+/// unchanged.
+pub fn invert_permutation(perm: &mut [usize]) {
+    check_permutation(perm);
+    for start in 0..perm.len() {
+        if perm[start] & MARK != 0 {
+            continue;
+        }
+        // Walk the cycle, pointing each index back at its predecessor.
+        let mut prev = start;
+        let mut cur = perm[start];
+        while cur != start {
+            let next = perm[cur];
+            perm[cur] = prev | MARK;
+            prev = cur;
+            cur = next;
+        }
+        perm[start] = prev | MARK;
+    }
+    clear_marks(perm);
+}
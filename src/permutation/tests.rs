@@ -0,0 +1,163 @@
+use super::{
+    apply_permutation, argsort, argsort_by, invert_permutation, try_argsort_by,
+    try_sort_by_transactional,
+};
+use crate::test_util::{keys, shuffled};
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+
+#[test]
+fn empty() {
+    let list: [u32; 0] = [];
+    assert!(argsort(&list).is_empty());
+}
+
+#[test]
+fn short() {
+    let list = [30, 10, 20, 10];
+    assert_eq!(argsort(&list), [1, 3, 2, 0]);
+    // The list itself is untouched.
+    assert_eq!(list, [30, 10, 20, 10]);
+}
+
+/// Equal elements stay in index order, on the merge path too.
+#[test]
+fn stable() {
+    let list: Vec<usize> = keys(300, 13).collect();
+    let perm = argsort(&list);
+    for pair in perm.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        assert!(list[a] <= list[b]);
+        if list[a] == list[b] {
+            assert!(a < b);
+        }
+    }
+    let mut sorted = list.clone();
+    crate::sort(&mut sorted);
+    assert!(perm.iter().map(|&i| list[i]).eq(sorted));
+}
+
+#[test]
+fn descending() {
+    let list: Vec<i32> = (0..100).collect();
+    let perm = argsort_by(&list, |a, b| b.cmp(a));
+    assert!(perm.into_iter().eq((0..100).rev()));
+}
+
+#[test]
+fn error() {
+    let list: Vec<i32> = (0..100).rev().collect();
+    let ret = try_argsort_by(&list, |a, b| if *a == 42 { Err(*b) } else { Ok(a.cmp(b)) });
+    assert!(ret.is_err());
+}
+
+/// Sort several parallel columns by one of them.
+#[test]
+fn apply() {
+    let mut keys: Vec<u32> = (0..200).map(|i| (i * 31) % 17).collect();
+    let mut names: Vec<String> = (0..200).map(|i| i.to_string()).collect();
+    let mut perm = argsort(&keys);
+    let saved = perm.clone();
+    apply_permutation(&mut keys, &mut perm);
+    apply_permutation(&mut names, &mut perm);
+    // The permutation is reusable.
+    assert_eq!(perm, saved);
+    for (i, &j) in perm.iter().enumerate() {
+        assert_eq!(names[i], j.to_string());
+        assert_eq!(keys[i], (j as u32 * 31) % 17);
+    }
+    assert!(keys.windows(2).all(|w| w[0] <= w[1]));
+}
+
+#[test]
+fn apply_cycles() {
+    let mut list = ['a', 'b', 'c', 'd', 'e', 'f'];
+    // One 3-cycle, one 2-cycle and a fixed point.
+    let mut perm = [2, 0, 1, 4, 3, 5];
+    apply_permutation(&mut list, &mut perm);
+    assert_eq!(list, ['c', 'a', 'b', 'e', 'd', 'f']);
+}
+
+#[test]
+fn invert() {
+    let list = [30, 10, 20, 10, 50];
+    let mut perm = argsort(&list);
+    invert_permutation(&mut perm);
+    // Ranks of the elements.
+    assert_eq!(perm, [3, 0, 2, 1, 4]);
+    invert_permutation(&mut perm);
+    assert_eq!(perm, argsort(&list));
+
+    let mut perm: Vec<usize> = (0..100).map(|i| (i * 37) % 100).collect();
+    let original = perm.clone();
+    invert_permutation(&mut perm);
+    for (i, &j) in original.iter().enumerate() {
+        assert_eq!(perm[j], i);
+    }
+}
+
+#[test]
+#[should_panic(expected = "duplicate")]
+fn duplicate_index() {
+    apply_permutation(&mut [1, 2, 3], &mut [0, 2, 2]);
+}
+
+#[test]
+#[should_panic(expected = "out of range")]
+fn out_of_range() {
+    invert_permutation(&mut [0, 3, 1]);
+}
+
+#[test]
+#[should_panic(expected = "length mismatch")]
+fn length_mismatch() {
+    apply_permutation(&mut [1, 2, 3], &mut [0, 1]);
+}
+
+#[test]
+fn transactional() {
+    let mut list: Vec<(usize, usize)> = keys(300, 13).zip(0..).collect();
+    let mut expected = list.clone();
+    expected.sort_by_key(|x| x.0);
+    assert_eq!(
+        try_sort_by_transactional(&mut list, |a, b| -> Result<_, ()> { Ok(a.0.cmp(&b.0)) }),
+        Ok(())
+    );
+    assert_eq!(list, expected);
+}
+
+/// A failed sort leaves the list exactly as it was, however far it got.
+#[test]
+fn transactional_rollback() {
+    let original = shuffled(300);
+    for fail_at in [0, 10, 500, 1500] {
+        let calls = Cell::new(0);
+        let mut list = original.clone();
+        let ret = try_sort_by_transactional(&mut list, |a, b| {
+            calls.set(calls.get() + 1);
+            if calls.get() > fail_at {
+                Err(calls.get())
+            } else {
+                Ok(a.cmp(b))
+            }
+        });
+        assert_eq!(ret, Err(fail_at + 1));
+        assert_eq!(list, original);
+    }
+}
+
+#[test]
+fn transactional_panic() {
+    let original: Vec<u32> = (0..300).rev().collect();
+    let mut list = original.clone();
+    let ret = panic::catch_unwind(AssertUnwindSafe(|| {
+        try_sort_by_transactional(&mut list, |a, b| -> Result<_, ()> {
+            if *a == 150 {
+                panic!("comparator");
+            }
+            Ok(a.cmp(b))
+        })
+    }));
+    assert!(ret.is_err());
+    assert_eq!(list, original);
+}
@@ -0,0 +1,260 @@
+//! Reporting how far a long sort has got, for progress bars.
+
+#[cfg(test)]
+mod tests;
+
+use crate::merge::MoveKind;
+use crate::sort::{calc_min_merge, sort_with, Run, RunStack};
+use crate::{never, no_values, ord_comparator, Comparator, NeverResult};
+use alloc::alloc::handle_alloc_error;
+use core::cell::RefCell;
+use core::cmp::Ordering;
+
+/// How far a sort has got, as passed to the callback of
+/// `sort_by_with_progress`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// The length of the list.
+    pub len: usize,
+    /// Runs found in the list so far.
+    pub runs_found: usize,
+    /// How much of the list run detection has got through.
+    pub scanned: usize,
+    /// How many elements merges have put in place so far, counting each
+    /// element once per merge it's in. This goes up during a merge as well as
+    /// at the end of it.
+    pub merged: usize,
+    /// An estimate of what `scanned + merged` will be once the list is
+    /// sorted, or 0 before there's anything to go on. It's worked out by
+    /// assuming the rest of the list has runs as long as the ones found so
+    /// far, and it's exact once run detection is done.
+    pub total: usize,
+    /// Whether the list is sorted.
+    pub finished: bool,
+}
+
+impl Progress {
+    /// How much of the sort is done, from 0 to 1: `scanned + merged` out of
+    /// `total`. It never goes down from one report to the next, and it's only
+    /// 1 once the list is sorted.
+    pub fn fraction(&self) -> f64 {
+        if self.finished {
+            1.0
+        } else if self.total == 0 {
+            0.0
+        } else {
+            // Not finished, so don't claim to be, even if the estimate was
+            // too low.
+            let done = (self.scanned + self.merged) as f64 / self.total as f64;
+            done.min(MAX_UNFINISHED)
+        }
+    }
+}
+
+/// The highest `fraction` before the sort is finished.
+const MAX_UNFINISHED: f64 = 0.99;
+
+/// About how many reports to make from inside merges, over the whole sort.
+const MERGE_REPORTS: usize = 1000;
+
+/// A comparator that reports progress as runs are found and merged.
+struct Reporting<C, F> {
+    cmp: C,
+    /// How short a run can be before the sort lengthens it.
+    min_run: usize,
+    state: RefCell<State<F>>,
+}
+
+struct State<F> {
+    progress: Progress,
+    on_progress: F,
+    /// The runs the sort is waiting to merge, kept the same way the sort
+    /// keeps them, to predict the merges still to come.
+    runs: RunStack,
+    /// `merged` at the start of the current merge.
+    merge_base: usize,
+    /// The last `fraction` reported, which it mustn't go below.
+    floor: f64,
+    /// The `scanned + merged` at which to report next from inside a merge.
+    next_report: usize,
+}
+
+impl<F: FnMut(&Progress)> State<F> {
+    fn report(&mut self) {
+        let progress = &mut self.progress;
+        let done = progress.scanned + progress.merged;
+        if progress.finished {
+            progress.total = done;
+        }
+        self.floor = progress.fraction();
+        let total = progress.total.max(progress.len);
+        self.next_report = done + (total / MERGE_REPORTS).max(1);
+        (self.on_progress)(progress);
+    }
+
+    /// A run is done being found, and is about to be pushed onto the sort's
+    /// run stack.
+    fn run_done(&mut self, run: Run) {
+        let progress = &self.progress;
+        if progress.runs_found.is_power_of_two() || run.pos + run.len == progress.len {
+            let estimate = self.estimate(run);
+            let done = progress.scanned + progress.merged;
+            let floor_total = (done as f64 / self.floor) as usize;
+            self.progress.total = if self.floor > 0.0 && floor_total < estimate {
+                // Keep `fraction` from going down.
+                floor_total
+            } else {
+                estimate
+            };
+        }
+        self.runs
+            .push(
+                run,
+                |layout| handle_alloc_error(layout),
+                &mut |_, _| -> NeverResult<_> { Ok(()) },
+            )
+            .unwrap_or_else(never);
+    }
+
+    /// Predict the final `scanned + merged`, by running the sort's merge
+    /// policy on `run` and then runs as long as the average so far.
+    fn estimate(&self, run: Run) -> usize {
+        let progress = &self.progress;
+        let mut merged = progress.merged;
+        let mut count = |run1: Run, run2: Run| -> NeverResult<_> {
+            merged += run1.len + run2.len;
+            Ok(())
+        };
+        let mut runs = self.runs.clone();
+        runs.push(run, |layout| handle_alloc_error(layout), &mut count)
+            .unwrap_or_else(never);
+        let mut pos = run.pos + run.len;
+        let average = (pos / progress.runs_found).max(1);
+        while pos < progress.len {
+            let left = progress.len - pos;
+            let len = if left < 2 * average { left } else { average };
+            runs.push(
+                Run { pos, len },
+                |layout| handle_alloc_error(layout),
+                &mut count,
+            )
+            .unwrap_or_else(never);
+            pos += len;
+        }
+        runs.merge_force_collapse(&mut count).unwrap_or_else(never);
+        progress.len + merged
+    }
+}
+
+impl<C, F: FnMut(&Progress)> Reporting<C, F> {
+    fn update(&self, update: impl FnOnce(&mut State<F>)) {
+        update(&mut self.state.borrow_mut());
+    }
+}
+
+impl<T, C: Comparator<T>, F: FnMut(&Progress)> Comparator<T> for Reporting<C, F> {
+    type Error = C::Error;
+    #[inline(always)]
+    fn is_gt(&self, lhs: &T, rhs: &T) -> Result<bool, Self::Error> {
+        self.cmp.is_gt(lhs, rhs)
+    }
+    fn run_found(&self, pos: usize, len: usize, _reversed: bool) {
+        self.update(|state| {
+            let progress = &mut state.progress;
+            progress.runs_found += 1;
+            progress.scanned = pos + len;
+            progress.finished = len == progress.len;
+            if len >= self.min_run.min(progress.len - pos) {
+                // Long enough to be pushed as it is.
+                state.run_done(Run { pos, len });
+            }
+            state.report();
+        });
+    }
+    fn run_extended(&self, pos: usize, _len: usize, new_len: usize) {
+        self.update(|state| {
+            let progress = &mut state.progress;
+            progress.scanned = pos + new_len;
+            progress.finished = new_len == progress.len;
+            if progress.runs_found == 0 {
+                // A short list, sorted without looking for runs.
+                progress.runs_found = 1;
+            } else {
+                state.run_done(Run { pos, len: new_len });
+            }
+            state.report();
+        });
+    }
+    fn merge_start(&self, _pos: usize, _first_len: usize, _second_len: usize) {
+        self.update(|state| state.merge_base = state.progress.merged);
+    }
+    fn moved(&self, count: usize, _kind: MoveKind) {
+        self.update(|state| {
+            let progress = &mut state.progress;
+            progress.merged += count;
+            if progress.scanned + progress.merged >= state.next_report {
+                state.report();
+            }
+        });
+    }
+    fn merge_end(&self, _pos: usize, len: usize) {
+        self.update(|state| {
+            // Elements that were already in place weren't moved, but they're
+            // done too.
+            let progress = &mut state.progress;
+            progress.merged = state.merge_base + len;
+            progress.finished = len == progress.len;
+            state.report();
+        });
+    }
+}
+
+/// Sorts the list like `try_sort_by`, calling `on_progress` after each run is
+/// found and each merge is done, and about a thousand times over the course
+/// of the merges in between, so a long sort can show a progress bar. The last
+/// call has `finished` set, unless the comparator fails, in which case
+/// there are no more calls after it does.
+///
+/// The calls can be very frequent, so `on_progress` should be quick, or only
+/// do real work when the progress has changed enough.
+pub fn try_sort_by_with_progress<T, E, C, F>(
+    list: &mut [T],
+    cmp: C,
+    on_progress: F,
+) -> Result<(), E>
+where
+    C: Fn(&T, &T) -> Result<Ordering, E>,
+    F: FnMut(&Progress),
+{
+    let progress = Progress {
+        len: list.len(),
+        ..Progress::default()
+    };
+    let reporting = Reporting {
+        cmp: ord_comparator(cmp),
+        min_run: calc_min_merge(list.len()),
+        state: RefCell::new(State {
+            progress,
+            on_progress,
+            runs: RunStack::new(),
+            merge_base: 0,
+            floor: 0.0,
+            next_report: 0,
+        }),
+    };
+    sort_with(list, no_values(list.len()), &reporting)
+}
+
+#[inline]
+pub fn sort_by_with_progress<T, C, F>(list: &mut [T], cmp: C, on_progress: F)
+where
+    C: Fn(&T, &T) -> Ordering,
+    F: FnMut(&Progress),
+{
+    try_sort_by_with_progress(
+        list,
+        move |a, b| -> NeverResult<_> { Ok(cmp(a, b)) },
+        on_progress,
+    )
+    .unwrap_or_else(never)
+}
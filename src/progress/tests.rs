@@ -0,0 +1,169 @@
+//! Progress reporting.
+
+use super::Progress;
+use crate::test_util::{shuffled, xorshift};
+use crate::{sort_by_with_progress, try_sort_by_with_progress};
+
+#[test]
+fn random() {
+    let mut list = shuffled(10_000);
+    let mut reports: Vec<Progress> = Vec::new();
+    sort_by_with_progress(&mut list, |a, b| a.cmp(b), |p| reports.push(*p));
+    assert!(list.iter().enumerate().all(|(i, &x)| i as u32 == x));
+
+    let last = reports.last().unwrap();
+    assert!(last.finished);
+    assert_eq!(last.scanned, 10_000);
+    assert_eq!(last.fraction(), 1.0);
+    assert!(reports[..reports.len() - 1].iter().all(|p| !p.finished));
+    for pair in reports.windows(2) {
+        assert!(pair[0].scanned <= pair[1].scanned);
+        assert!(pair[0].merged <= pair[1].merged);
+        assert!(pair[0].runs_found <= pair[1].runs_found);
+    }
+    for p in &reports {
+        assert_eq!(p.len, 10_000);
+        assert!((0.0..=1.0).contains(&p.fraction()));
+    }
+    for pair in reports.windows(2) {
+        assert!(pair[0].fraction() <= pair[1].fraction());
+    }
+    assert!(last.merged >= 10_000);
+}
+
+#[test]
+fn sorted() {
+    let mut list: Vec<u32> = (0..1000).collect();
+    let mut reports: Vec<Progress> = Vec::new();
+    sort_by_with_progress(&mut list, |a, b| a.cmp(b), |p| reports.push(*p));
+    assert_eq!(
+        reports,
+        [Progress {
+            len: 1000,
+            runs_found: 1,
+            scanned: 1000,
+            merged: 0,
+            total: 1000,
+            finished: true,
+        }]
+    );
+}
+
+#[test]
+fn short() {
+    for len in [0, 1, 10] {
+        let mut list: Vec<u32> = (0..len).rev().collect();
+        let mut reports: Vec<Progress> = Vec::new();
+        sort_by_with_progress(&mut list, |a, b| a.cmp(b), |p| reports.push(*p));
+        assert_eq!(reports.len(), 1);
+        assert!(reports[0].finished);
+    }
+}
+
+#[test]
+fn error() {
+    let mut list: Vec<u32> = (0..1000).rev().collect();
+    let mut calls = 0;
+    let ret = try_sort_by_with_progress(&mut list, |_, _| Err(()), |_| calls += 1);
+    assert_eq!(ret, Err(()));
+    assert_eq!(calls, 0);
+}
+
+/// Once the comparator has failed, `on_progress` isn't called again, even
+/// though the merge it failed in still moves elements back into the list.
+#[test]
+fn no_reports_after_failure() {
+    use std::cell::Cell;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    // Two long runs, and a failure about a hundred comparisons into merging
+    // them.
+    let original: Vec<u32> = (0..5000)
+        .map(|i| 2 * i)
+        .chain((0..5000).map(|i| 2 * i + 1))
+        .collect();
+    for &panics in &[false, true] {
+        let failed = Cell::new(false);
+        let compares = Cell::new(0);
+        let mut calls = 0;
+        let mut list = original.clone();
+        let ret = catch_unwind(AssertUnwindSafe(|| {
+            try_sort_by_with_progress(
+                &mut list,
+                |a, b| {
+                    compares.set(compares.get() + 1);
+                    if compares.get() > 10_100 {
+                        failed.set(true);
+                        if panics {
+                            panic!("Expected panic: this is normal");
+                        }
+                        return Err(());
+                    }
+                    Ok(a.cmp(b))
+                },
+                |_| {
+                    assert!(!failed.get());
+                    calls += 1;
+                },
+            )
+        }));
+        match ret {
+            Ok(ret) => assert!(!panics && ret == Err(())),
+            Err(_) => assert!(panics),
+        }
+        assert!(calls > 0);
+        list.sort();
+        let mut expected = original.clone();
+        expected.sort();
+        assert_eq!(list, expected);
+    }
+}
+
+#[test]
+fn fraction() {
+    let mut p = Progress {
+        len: 1000,
+        ..Progress::default()
+    };
+    assert_eq!(p.fraction(), 0.0);
+    p.scanned = 500;
+    p.merged = 1500;
+    p.total = 8000;
+    assert_eq!(p.fraction(), 0.25);
+    // An estimate that was too low doesn't make it look finished.
+    p.merged = 7500;
+    assert!(p.fraction() < 1.0);
+    p.finished = true;
+    assert_eq!(p.fraction(), 1.0);
+}
+
+/// On random input the merges are most of the work, so the estimate has to
+/// follow them: it never goes down, and stays close to the real share of the
+/// work done.
+#[test]
+fn fraction_tracks_work() {
+    let mut x = 1;
+    let mut list: Vec<u64> = (0..20_000)
+        .map(|_| {
+            x = xorshift(x);
+            x
+        })
+        .collect();
+    let mut reports: Vec<Progress> = Vec::new();
+    sort_by_with_progress(&mut list, |a, b| a.cmp(b), |p| reports.push(*p));
+    let last = reports.last().unwrap();
+    assert!(last.finished);
+    let total = (last.scanned + last.merged) as f64;
+    for pair in reports.windows(2) {
+        assert!(pair[0].fraction() <= pair[1].fraction(), "{:?}", pair);
+    }
+    for p in &reports {
+        let done = (p.scanned + p.merged) as f64 / total;
+        assert!((p.fraction() - done).abs() < 0.05, "{:?}", p);
+    }
+    // Reports keep coming during the merges, not just between them.
+    let merging = reports
+        .windows(2)
+        .filter(|pair| pair[0].scanned == pair[1].scanned && pair[0].merged < pair[1].merged);
+    assert!(merging.count() > 100);
+}
@@ -7,16 +7,17 @@ mod tests;
 use crate::find_run::get_run;
 use crate::insort;
 use crate::merge::merge;
-use crate::Comparator;
+use crate::{no_values, Comparator};
 use alloc::vec::Vec;
+use core::alloc::Layout;
 use core::cmp::min;
 
 /// Minimum run length to merge; anything shorter will be lengthend and
 /// sorted using `insort::sort`.
-const MIN_MERGE: usize = 64;
+pub(crate) const MIN_MERGE: usize = 64;
 
 /// Compute the actual minimum merge size for a particular list.
-fn calc_min_merge(mut len: usize) -> usize {
+pub(crate) fn calc_min_merge(mut len: usize) -> usize {
     if len < MIN_MERGE {
         len
     } else {
@@ -31,66 +32,53 @@ fn calc_min_merge(mut len: usize) -> usize {
 
 /// Represents a known-sorted sublist.
 #[derive(Copy, Clone, Debug)]
-struct Run {
-    pos: usize,
-    len: usize,
+pub(crate) struct Run {
+    pub(crate) pos: usize,
+    pub(crate) len: usize,
 }
 
-/// All the ongoing state of the sort.
-struct SortState<'a, T, C: Comparator<T>> {
-    /// The list that is being sorted.
-    list: &'a mut [T],
-    /// The comparator function. Should return true if the first argument is
-    /// greater than the second.
-    cmp: &'a C,
-    /// The list of known-sorted sections of the list that can be merged.
+/// The list of known-sorted sections of the list that can be merged, and the
+/// policy for when to merge them. The merging itself is up to the caller, so
+/// this can drive sorts over things other than slices.
+#[derive(Clone)]
+pub(crate) struct RunStack {
     /// To keep the size of this list down, this invariant is preserved:
     ///  - `runs.len < 3 || runs[i-2].len > runs[i-1].len + runs[i].len`
     ///  - `runs.len < 2 || runs[i-1].len > runs[i].len`
     runs: Vec<Run>,
-    /// The current position in the list. When `pos == list.len()`, we can now
-    /// merge the last of the runs, and we're done.
-    pos: usize,
 }
 
-impl<'a, T, C: Comparator<T>> SortState<'a, T, C> {
+impl RunStack {
     #[inline]
-    fn new(list: &'a mut [T], cmp: &'a C) -> SortState<'a, T, C> {
-        SortState {
-            list,
-            cmp,
-            runs: Vec::new(),
-            pos: 0,
-        }
+    pub(crate) fn new() -> RunStack {
+        RunStack { runs: Vec::new() }
     }
 
-    /// The outer loop. Find runs, and move forward.
-    fn sort(&mut self) -> Result<(), C::Error> {
-        let list_len = self.list.len();
-        // Minimum run size to use merge sort on. Any sorted sections of the
-        // list that are shorter than this are lengthened using `insort::sort`.
-        let min_run = calc_min_merge(list_len);
-        while self.pos < list_len {
-            let pos = self.pos;
-            let mut run_len = get_run(&mut self.list[pos..], self.cmp)?;
-            let run_min_len = min(min_run, list_len - pos);
-            if run_len < run_min_len {
-                run_len = run_min_len;
-                let l = &mut self.list[pos..][..run_len];
-                insort::sort(l, self.cmp)?;
-            }
-            self.runs.push(Run { pos, len: run_len });
-            self.pos += run_len;
-            self.merge_collapse()?;
+    /// Add a newly found run to the top of the stack, and merge runs if
+    /// they're too big. `merge` is passed two adjacent runs to merge. If the
+    /// stack can't grow to hold the run, `alloc_failed` is passed the layout
+    /// that couldn't be allocated, and returns the error to stop with.
+    pub(crate) fn push<E>(
+        &mut self,
+        run: Run,
+        alloc_failed: impl FnOnce(Layout) -> E,
+        merge: &mut impl FnMut(Run, Run) -> Result<(), E>,
+    ) -> Result<(), E> {
+        if self.runs.try_reserve(1).is_err() {
+            let layout = Layout::array::<Run>(self.runs.len() + 1).unwrap();
+            return Err(alloc_failed(layout));
         }
-        self.merge_force_collapse()?;
-        Ok(())
+        self.runs.push(run);
+        self.merge_collapse(merge)
     }
 
     /// Merge the runs if they're too big.
     /// Copied almost verbatim from
     /// http://envisage-project.eu/proving-android-java-and-python-sorting-algorithm-is-broken-and-how-to-fix-it/#sec3.2
-    fn merge_collapse(&mut self) -> Result<(), C::Error> {
+    fn merge_collapse<E>(
+        &mut self,
+        merge: &mut impl FnMut(Run, Run) -> Result<(), E>,
+    ) -> Result<(), E> {
         let runs = &mut self.runs;
         while runs.len() > 1 {
             let l = runs.len();
@@ -109,8 +97,7 @@ impl<'a, T, C: Comparator<T>> SortState<'a, T, C> {
                     pos: run1.pos,
                     len: run1.len + run2.len,
                 };
-                let l = &mut self.list[run1.pos..][..run1.len + run2.len];
-                merge(l, run1.len, self.cmp)?;
+                merge(run1, run2)?;
             } else {
                 break; // Invariant established.
             }
@@ -119,7 +106,10 @@ impl<'a, T, C: Comparator<T>> SortState<'a, T, C> {
     }
 
     /// Merge any outstanding runs, at the end.
-    fn merge_force_collapse(&mut self) -> Result<(), C::Error> {
+    pub(crate) fn merge_force_collapse<E>(
+        &mut self,
+        merge: &mut impl FnMut(Run, Run) -> Result<(), E>,
+    ) -> Result<(), E> {
         let runs = &mut self.runs;
         while runs.len() > 1 {
             let (mut pos1, mut pos2) = (runs.len() - 2, runs.len() - 1);
@@ -134,18 +124,139 @@ impl<'a, T, C: Comparator<T>> SortState<'a, T, C> {
                 pos: run1.pos,
                 len: run1.len + run2.len,
             };
-            let l = &mut self.list[run1.pos..][..run1.len + run2.len];
-            merge(l, run1.len, self.cmp)?;
+            merge(run1, run2)?;
+        }
+        Ok(())
+    }
+}
+
+/// All the ongoing state of the sort.
+struct SortState<'a, T, V, C: Comparator<T>> {
+    /// The list that is being sorted.
+    list: &'a mut [T],
+    /// Values that get moved in lockstep with the list. For most sorts, these
+    /// are zero-sized `()`.
+    values: &'a mut [V],
+    /// The comparator function. Should return true if the first argument is
+    /// greater than the second.
+    cmp: &'a C,
+    /// The runs waiting to be merged.
+    runs: RunStack,
+    /// The current position in the list. When `pos == list.len()`, we can now
+    /// merge the last of the runs, and we're done.
+    pos: usize,
+}
+
+impl<'a, T, V, C: Comparator<T>> SortState<'a, T, V, C> {
+    #[inline]
+    fn new(list: &'a mut [T], values: &'a mut [V], cmp: &'a C) -> SortState<'a, T, V, C> {
+        SortState {
+            list,
+            values,
+            cmp,
+            runs: RunStack::new(),
+            pos: 0,
+        }
+    }
+
+    /// The outer loop. Find runs, and move forward.
+    fn sort(&mut self) -> Result<(), C::Error> {
+        let list_len = self.list.len();
+        // Minimum run size to use merge sort on. Any sorted sections of the
+        // list that are shorter than this are lengthened using `insort::sort`.
+        let min_run = calc_min_merge(list_len);
+        while self.pos < list_len {
+            let pos = self.pos;
+            let (mut run_len, reversed) =
+                get_run(&mut self.list[pos..], &mut self.values[pos..], self.cmp)?;
+            self.cmp.run_found(pos, run_len, reversed);
+            let run_min_len = min(min_run, list_len - pos);
+            if run_len < run_min_len {
+                let l = &mut self.list[pos..][..run_min_len];
+                let v = &mut self.values[pos..][..run_min_len];
+                insort::sort(l, v, self.cmp)?;
+                self.cmp.run_extended(pos, run_len, run_min_len);
+                run_len = run_min_len;
+            }
+            self.pos += run_len;
+            let (list, values, cmp) = (&mut *self.list, &mut *self.values, self.cmp);
+            self.runs.push(
+                Run { pos, len: run_len },
+                |layout| cmp.alloc_failed(layout),
+                &mut |run1, run2| merge_runs(list, values, run1, run2, cmp),
+            )?;
         }
+        let (list, values, cmp) = (&mut *self.list, &mut *self.values, self.cmp);
+        self.runs
+            .merge_force_collapse(&mut |run1, run2| merge_runs(list, values, run1, run2, cmp))?;
         Ok(())
     }
 }
 
+/// Merge two adjacent runs of the list.
+#[inline]
+fn merge_runs<T, V, C: Comparator<T>>(
+    list: &mut [T],
+    values: &mut [V],
+    run1: Run,
+    run2: Run,
+    cmp: &C,
+) -> Result<(), C::Error> {
+    let l = &mut list[run1.pos..][..run1.len + run2.len];
+    let v = &mut values[run1.pos..][..run1.len + run2.len];
+    cmp.merge_start(run1.pos, run1.len, run2.len);
+    merge(l, v, run1.len, cmp)?;
+    cmp.merge_end(run1.pos, run1.len + run2.len);
+    Ok(())
+}
+
 /// Sorts the list using merge sort.
 pub(crate) fn try_sort_by<T, C: Comparator<T>>(list: &mut [T], cmp: C) -> Result<(), C::Error> {
+    let values = no_values(list.len());
+    try_sort_zip_by(list, values, cmp)
+}
+
+/// Sorts the list using merge sort, applying every move to `values` as well.
+pub(crate) fn try_sort_zip_by<T, V, C: Comparator<T>>(
+    list: &mut [T],
+    values: &mut [V],
+    cmp: C,
+) -> Result<(), C::Error> {
+    sort_with(list, values, &cmp)
+}
+
+/// Sorts the list using merge sort, borrowing the comparator so it can be
+/// shared, e.g. between threads.
+pub(crate) fn sort_with<T, V, C: Comparator<T>>(
+    list: &mut [T],
+    values: &mut [V],
+    cmp: &C,
+) -> Result<(), C::Error> {
+    assert_eq!(list.len(), values.len(), "keys and values differ in length");
     if list.len() < MIN_MERGE {
-        insort::sort(list, &cmp)
+        // The whole list is one short run.
+        insort::sort(list, values, cmp)?;
+        cmp.run_extended(0, 0, list.len());
+        Ok(())
     } else {
-        SortState::new(list, &cmp).sort()
+        SortState::new(list, values, cmp).sort()
+    }
+}
+
+/// Sorts the list with CPython's `list.sort(reverse=True)` semantics when
+/// `reverse` is set: the list is reversed, sorted, and reversed again, so the
+/// result is descending while equal elements keep their original order. As in
+/// CPython, the final reversal happens even if the comparator fails.
+pub(crate) fn try_sort_by_reverse<T, C: Comparator<T>>(
+    list: &mut [T],
+    cmp: C,
+    reverse: bool,
+) -> Result<(), C::Error> {
+    if !reverse {
+        return try_sort_by(list, cmp);
     }
+    list.reverse();
+    let ret = try_sort_by(list, cmp);
+    list.reverse();
+    ret
 }
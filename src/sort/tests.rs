@@ -1,7 +1,7 @@
 //! The top sorting algorithm; that is, the modified merge sort we keep
 //! talking about.
 
-use crate::{never, ord_t_comparator};
+use crate::{never, no_values, ord_t_comparator, test_util};
 
 /// Test the sort implementation with an empty list
 #[test]
@@ -107,9 +107,73 @@ fn stable() {
     }
 }
 
+/// Descending sorts keep equal elements in their original order, both for
+/// the insertion sort path and the merge path.
+#[test]
+fn reverse_stable() {
+    for &len in &[37usize, 259] {
+        let list: Vec<(usize, usize)> = (0..len).map(|i| (i % 5, i)).collect();
+        let mut desc = list.clone();
+        crate::sort_by_desc(&mut desc, |a, b| a.0.cmp(&b.0));
+        for i in 0..(len - 1) {
+            assert!(desc[i].0 >= desc[i + 1].0);
+            if desc[i].0 == desc[i + 1].0 {
+                assert!(desc[i].1 < desc[i + 1].1);
+            }
+        }
+        // Flipping the comparator gives the same stable result.
+        let mut flipped = list.clone();
+        crate::sort_by(&mut flipped, |a, b| b.0.cmp(&a.0));
+        assert_eq!(desc, flipped);
+        // Without the flag, it's a plain ascending sort.
+        let mut asc = list.clone();
+        crate::sort_by_reverse(&mut asc, |a, b| a.0.cmp(&b.0), false);
+        let mut expected = list;
+        crate::sort_by(&mut expected, |a, b| a.0.cmp(&b.0));
+        assert_eq!(asc, expected);
+    }
+}
+
+/// Like CPython, the list is reversed back even when the comparator fails.
+#[test]
+fn reverse_error() {
+    let mut list: Vec<usize> = (0..100).collect();
+    let ret = crate::try_sort_by_reverse(&mut list, |_, _| Err(()), true);
+    assert_eq!(ret, Err(()));
+    assert!(list.iter().copied().eq(0..100));
+}
+
+/// Sorting keys moves values with them, through runs, insertion sort and
+/// merges, and equal keys keep their values in order.
+#[test]
+fn zip() {
+    let len = 1000;
+    let mut keys: Vec<usize> = test_util::keys(len, 97).collect();
+    // A descending run, to exercise reversal.
+    keys[100..300].sort_by(|a, b| b.cmp(a));
+    let mut values: Vec<usize> = (0..len).collect();
+    let original = keys.clone();
+    crate::sort_zip(&mut keys, &mut values);
+    for i in 0..len {
+        assert_eq!(keys[i], original[values[i]]);
+    }
+    for i in 0..(len - 1) {
+        assert!(keys[i] <= keys[i + 1]);
+        if keys[i] == keys[i + 1] {
+            assert!(values[i] < values[i + 1]);
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "differ in length")]
+fn zip_length_mismatch() {
+    crate::sort_zip(&mut [1, 2, 3], &mut [1, 2]);
+}
+
 /// Sort implementation convenience used for tests.
 fn sort<T: Ord>(list: &mut [T]) {
-    super::SortState::new(list, &ord_t_comparator())
+    super::SortState::new(list, no_values(list.len()), &ord_t_comparator())
         .sort()
         .unwrap_or_else(never)
 }
@@ -0,0 +1,209 @@
+//! Counting what the sort does, for tuning comparators and input orderings.
+
+#[cfg(test)]
+mod tests;
+
+use crate::merge::MoveKind;
+use crate::sort::sort_with;
+use crate::{never, no_values, ord_comparator, Comparator, NeverResult};
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::cmp::{max, Ordering};
+use core::mem::size_of;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+/// What a sort did, as counted by `sort_by_with_stats`.
+///
+/// The work done by merges is counted in elements moved and comparisons made,
+/// so the numbers don't depend on the machine. With the `std` feature, the
+/// time spent merging is measured as well.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SortStats {
+    /// Calls to the comparator.
+    pub comparisons: usize,
+    /// Runs found in the input, before short ones were lengthened. A list
+    /// too short to be worth merging counts as one run of its whole length.
+    pub runs: usize,
+    /// A histogram of those runs' lengths: `run_lengths[k]` is how many had a
+    /// length in `2^k..2^(k+1)`.
+    pub run_lengths: Vec<usize>,
+    /// Runs that were strictly descending, and so were reversed.
+    pub reversed_runs: usize,
+    /// Elements added to runs by insertion sort. A list too short to be worth
+    /// merging is insertion sorted as a whole.
+    pub insertion_elements: usize,
+    /// Comparisons made by insertion sort.
+    pub insertion_comparisons: usize,
+    /// Pairs of runs merged.
+    pub merges: usize,
+    /// Comparisons made while merging.
+    pub merge_comparisons: usize,
+    /// Elements moved one at a time while merging.
+    pub linear_moves: usize,
+    /// Elements moved in bulk after galloping.
+    pub gallop_moves: usize,
+    /// Elements moved in bulk once the other run was used up.
+    pub remainder_moves: usize,
+    /// Times a merge switched to galloping.
+    pub gallops: usize,
+    /// Comparisons made while galloping.
+    pub gallop_comparisons: usize,
+    /// The most elements held in temporary storage at once.
+    pub peak_scratch: usize,
+    /// `peak_scratch`, in bytes.
+    pub peak_scratch_bytes: usize,
+    /// Time spent galloping. Only measured with the `std` feature, and zero
+    /// otherwise.
+    pub gallop_time: Duration,
+    /// The rest of the time spent merging, mostly moving elements one at a
+    /// time. Only measured with the `std` feature, and zero otherwise.
+    pub linear_time: Duration,
+}
+
+impl SortStats {
+    fn add_run(&mut self, len: usize) {
+        self.runs += 1;
+        let bucket = (usize::BITS - 1 - len.leading_zeros()) as usize;
+        if self.run_lengths.len() <= bucket {
+            self.run_lengths.resize(bucket + 1, 0);
+        }
+        self.run_lengths[bucket] += 1;
+    }
+}
+
+/// When the current merge and gallop started, to time them.
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct Clock {
+    merge_start: Option<Instant>,
+    gallop_start: Option<Instant>,
+    /// Time spent galloping in the current merge.
+    merge_gallop_time: Duration,
+}
+
+struct State {
+    stats: SortStats,
+    /// The comparison count when the current run or merge started.
+    mark: usize,
+    galloping: bool,
+    #[cfg(feature = "std")]
+    clock: Clock,
+}
+
+/// A comparator that fills in `SortStats` as the sort calls its hooks.
+struct Counting<C> {
+    cmp: C,
+    state: RefCell<State>,
+}
+
+impl<T, C: Comparator<T>> Comparator<T> for Counting<C> {
+    type Error = C::Error;
+    fn is_gt(&self, lhs: &T, rhs: &T) -> Result<bool, Self::Error> {
+        let mut state = self.state.borrow_mut();
+        state.stats.comparisons += 1;
+        if state.galloping {
+            state.stats.gallop_comparisons += 1;
+        }
+        drop(state);
+        self.cmp.is_gt(lhs, rhs)
+    }
+    fn run_found(&self, _pos: usize, len: usize, reversed: bool) {
+        let state = &mut *self.state.borrow_mut();
+        let stats = &mut state.stats;
+        stats.add_run(len);
+        if reversed {
+            stats.reversed_runs += 1;
+        }
+        state.mark = stats.comparisons;
+    }
+    fn run_extended(&self, _pos: usize, len: usize, new_len: usize) {
+        let state = &mut *self.state.borrow_mut();
+        if len == 0 && new_len != 0 {
+            // A short list, sorted without looking for runs.
+            state.stats.add_run(new_len);
+        }
+        state.stats.insertion_elements += new_len - len;
+        state.stats.insertion_comparisons += state.stats.comparisons - state.mark;
+    }
+    fn merge_start(&self, _pos: usize, _first_len: usize, _second_len: usize) {
+        let state = &mut *self.state.borrow_mut();
+        state.stats.merges += 1;
+        state.mark = state.stats.comparisons;
+        #[cfg(feature = "std")]
+        {
+            state.clock.merge_start = Some(Instant::now());
+            state.clock.merge_gallop_time = Duration::ZERO;
+        }
+    }
+    fn merge_end(&self, _pos: usize, _len: usize) {
+        let state = &mut *self.state.borrow_mut();
+        state.stats.merge_comparisons += state.stats.comparisons - state.mark;
+        #[cfg(feature = "std")]
+        if let Some(start) = state.clock.merge_start.take() {
+            let linear = start
+                .elapsed()
+                .saturating_sub(state.clock.merge_gallop_time);
+            state.stats.linear_time += linear;
+        }
+    }
+    fn scratch_used(&self, len: usize) {
+        let stats = &mut self.state.borrow_mut().stats;
+        stats.peak_scratch = max(stats.peak_scratch, len);
+        stats.peak_scratch_bytes = stats.peak_scratch * size_of::<T>();
+    }
+    fn gallop_enter(&self) {
+        let state = &mut *self.state.borrow_mut();
+        state.stats.gallops += 1;
+        state.galloping = true;
+        #[cfg(feature = "std")]
+        {
+            state.clock.gallop_start = Some(Instant::now());
+        }
+    }
+    fn gallop_exit(&self) {
+        let state = &mut *self.state.borrow_mut();
+        state.galloping = false;
+        #[cfg(feature = "std")]
+        if let Some(start) = state.clock.gallop_start.take() {
+            let elapsed = start.elapsed();
+            state.clock.merge_gallop_time += elapsed;
+            state.stats.gallop_time += elapsed;
+        }
+    }
+    fn moved(&self, count: usize, kind: MoveKind) {
+        let stats = &mut self.state.borrow_mut().stats;
+        match kind {
+            MoveKind::OneAtATime => stats.linear_moves += count,
+            MoveKind::Gallop => stats.gallop_moves += count,
+            MoveKind::Remainder => stats.remainder_moves += count,
+        }
+    }
+}
+
+/// Sorts the list like `try_sort_by`, counting what the sort does along the
+/// way.
+pub fn try_sort_by_with_stats<T, E, C: Fn(&T, &T) -> Result<Ordering, E>>(
+    list: &mut [T],
+    cmp: C,
+) -> Result<SortStats, E> {
+    let counting = Counting {
+        cmp: ord_comparator(cmp),
+        state: RefCell::new(State {
+            stats: SortStats::default(),
+            mark: 0,
+            galloping: false,
+            #[cfg(feature = "std")]
+            clock: Clock::default(),
+        }),
+    };
+    sort_with(list, no_values(list.len()), &counting)?;
+    Ok(counting.state.into_inner().stats)
+}
+
+#[inline]
+pub fn sort_by_with_stats<T, C: Fn(&T, &T) -> Ordering>(list: &mut [T], cmp: C) -> SortStats {
+    try_sort_by_with_stats(list, move |a, b| -> NeverResult<_> { Ok(cmp(a, b)) })
+        .unwrap_or_else(never)
+}
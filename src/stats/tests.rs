@@ -0,0 +1,87 @@
+//! Sort statistics.
+
+use crate::{sort_by_with_stats, try_sort_by_with_stats};
+use std::cell::Cell;
+use std::time::Duration;
+
+#[test]
+fn sorted() {
+    let mut list: Vec<u32> = (0..1000).collect();
+    let stats = sort_by_with_stats(&mut list, |a, b| a.cmp(b));
+    assert_eq!(stats.comparisons, 999);
+    assert_eq!(stats.runs, 1);
+    assert_eq!(stats.reversed_runs, 0);
+    assert_eq!(stats.run_lengths[9], 1);
+    assert_eq!(stats.merges, 0);
+    assert_eq!(stats.peak_scratch, 0);
+}
+
+#[test]
+fn reversed() {
+    let mut list: Vec<u32> = (0..1000).rev().collect();
+    let stats = sort_by_with_stats(&mut list, |a, b| a.cmp(b));
+    assert_eq!(stats.runs, 1);
+    assert_eq!(stats.reversed_runs, 1);
+    assert!(list.iter().enumerate().all(|(i, &x)| i as u32 == x));
+}
+
+#[test]
+fn short() {
+    let mut list = vec![3, 1, 2];
+    let stats = sort_by_with_stats(&mut list, |a, b| a.cmp(b));
+    assert_eq!(list, [1, 2, 3]);
+    // The whole list counts as one run.
+    assert_eq!(stats.runs, 1);
+    assert_eq!(stats.run_lengths, [0, 1]);
+    assert_eq!(stats.insertion_elements, 3);
+    assert_eq!(stats.insertion_comparisons, stats.comparisons);
+}
+
+#[test]
+fn merged() {
+    // Interleaved runs, so the merge has to move everything but the ends.
+    let mut list: Vec<u32> = (0..256).map(|x| x * 2).collect();
+    list.extend((0..256).map(|x| x * 2 + 1));
+    let calls = Cell::new(0);
+    let stats = sort_by_with_stats(&mut list, |a, b| {
+        calls.set(calls.get() + 1);
+        a.cmp(b)
+    });
+    assert!(list.iter().enumerate().all(|(i, &x)| i as u32 == x));
+    assert_eq!(stats.comparisons, calls.get());
+    assert_eq!(stats.runs, 2);
+    assert_eq!(stats.merges, 1);
+    assert_eq!(stats.peak_scratch, 255);
+    assert_eq!(stats.peak_scratch_bytes, 1020);
+    assert_eq!(
+        stats.linear_moves + stats.gallop_moves + stats.remainder_moves,
+        510
+    );
+    assert!(stats.merge_comparisons <= stats.comparisons);
+}
+
+#[test]
+fn gallops() {
+    // Long stretches from each run, so the merge switches to galloping.
+    let mut list: Vec<u32> = (0..8)
+        .flat_map(|i| (0..100).map(move |x| i * 200 + x))
+        .collect();
+    list.extend((0..8).flat_map(|i| (0..100).map(move |x| i * 200 + 100 + x)));
+    let stats = sort_by_with_stats(&mut list, |a, b| a.cmp(b));
+    assert!(list.windows(2).all(|w| w[0] < w[1]));
+    assert!(stats.gallops > 0);
+    assert!(stats.gallop_moves > stats.linear_moves);
+    assert!(stats.gallop_comparisons > 0);
+    if cfg!(feature = "std") {
+        assert!(stats.gallop_time + stats.linear_time > Duration::ZERO);
+    } else {
+        assert_eq!(stats.gallop_time + stats.linear_time, Duration::ZERO);
+    }
+}
+
+#[test]
+fn error() {
+    let mut list: Vec<u32> = (0..1000).rev().collect();
+    let ret = try_sort_by_with_stats(&mut list, |_, _| Err("fail"));
+    assert_eq!(ret, Err("fail"));
+}
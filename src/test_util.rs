@@ -0,0 +1,55 @@
+//! Inputs shared by the tests.
+
+/// `len` keys from `0..distinct`, in a scattered order with only short runs.
+/// Each key appears about `len / distinct` times, for checking stability.
+pub(crate) fn keys(len: usize, distinct: usize) -> impl Iterator<Item = usize> {
+    // 7919 is prime, so with `distinct == len` every key appears once as long
+    // as `len` isn't a multiple of it.
+    (0..len).map(move |i| (i * 7919) % distinct)
+}
+
+/// The numbers `0..len`, in a scattered order with only short runs.
+pub(crate) fn shuffled(len: usize) -> Vec<u32> {
+    keys(len, len).map(|x| x as u32).collect()
+}
+
+/// The next number after `x` from a xorshift generator, for pseudo-random
+/// inputs that are the same on every run. `x` must not be 0.
+pub(crate) fn xorshift(mut x: u64) -> u64 {
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    x
+}
+
+/// The system allocator, except that allocations bigger than a limit set with
+/// `fail_allocs_over` fail, on the thread that set it.
+struct LimitedAlloc;
+
+std::thread_local! {
+    static ALLOC_LIMIT: std::cell::Cell<usize> = const { std::cell::Cell::new(usize::MAX) };
+}
+
+unsafe impl std::alloc::GlobalAlloc for LimitedAlloc {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        if layout.size() > ALLOC_LIMIT.with(|limit| limit.get()) {
+            return std::ptr::null_mut();
+        }
+        std::alloc::System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        std::alloc::System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOC: LimitedAlloc = LimitedAlloc;
+
+/// Run `f` with every allocation of more than `limit` bytes on this thread
+/// failing.
+pub(crate) fn fail_allocs_over<R>(limit: usize, f: impl FnOnce() -> R) -> R {
+    let old = ALLOC_LIMIT.with(|cell| cell.replace(limit));
+    let ret = f();
+    ALLOC_LIMIT.with(|cell| cell.set(old));
+    ret
+}
@@ -0,0 +1,231 @@
+//! Recording the runs and merges of a sort, to save or draw later.
+
+#[cfg(test)]
+mod tests;
+
+use crate::observer::SortObserver;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+use core::ops::Range;
+use core::str::FromStr;
+
+/// One step of a sort, as recorded by a `Trace`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TraceEvent {
+    /// A run of `len` elements was found at `pos`, and reversed if it was
+    /// descending.
+    Run {
+        pos: usize,
+        len: usize,
+        reversed: bool,
+    },
+    /// The run at `pos` was lengthened from `len` to `new_len` elements with
+    /// insertion sort.
+    Extend {
+        pos: usize,
+        len: usize,
+        new_len: usize,
+    },
+    /// The runs at `pos` and `pos + first_len` were merged, switching to
+    /// galloping `gallops` times.
+    Merge {
+        pos: usize,
+        first_len: usize,
+        second_len: usize,
+        gallops: usize,
+    },
+}
+
+/// A `SortObserver` that records the runs and merges of a sort, so it can be
+/// saved and drawn later.
+///
+/// The text format, written by `Display` and read by `FromStr`, has one event
+/// per line:
+///
+/// ```text
+/// run <pos> <len> [reversed]
+/// extend <pos> <len> <new_len>
+/// merge <pos> <first_len> <second_len> <gallops>
+/// ```
+///
+/// ```
+/// use timsort::{sort_by_observed, Trace};
+///
+/// let mut list: Vec<u32> = (0..100).rev().collect();
+/// let mut trace = Trace::new();
+/// sort_by_observed(&mut list, |a, b| a.cmp(b), &mut trace);
+/// assert_eq!(trace.to_string(), "run 0 100 reversed\n");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Trace {
+    events: Vec<TraceEvent>,
+    /// The merge in progress, which is recorded once it's done.
+    merge: Option<TraceEvent>,
+}
+
+impl Trace {
+    #[inline]
+    pub fn new() -> Trace {
+        Trace::default()
+    }
+
+    /// The events recorded so far, in order.
+    #[inline]
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+
+    /// The events as a JSON object, with an `"events"` array.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\"events\":[");
+        for (i, event) in self.events.iter().enumerate() {
+            if i != 0 {
+                json.push(',');
+            }
+            // Writing to a `String` can't fail.
+            let _ = match *event {
+                TraceEvent::Run { pos, len, reversed } => write!(
+                    json,
+                    "{{\"type\":\"run\",\"pos\":{},\"len\":{},\"reversed\":{}}}",
+                    pos, len, reversed
+                ),
+                TraceEvent::Extend { pos, len, new_len } => write!(
+                    json,
+                    "{{\"type\":\"extend\",\"pos\":{},\"len\":{},\"new_len\":{}}}",
+                    pos, len, new_len
+                ),
+                TraceEvent::Merge {
+                    pos,
+                    first_len,
+                    second_len,
+                    gallops,
+                } => write!(
+                    json,
+                    "{{\"type\":\"merge\",\"pos\":{},\"first_len\":{},\"second_len\":{},\"gallops\":{}}}",
+                    pos, first_len, second_len, gallops
+                ),
+            };
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+impl SortObserver for Trace {
+    fn on_run_found(&mut self, pos: usize, len: usize, reversed: bool) {
+        self.events.push(TraceEvent::Run { pos, len, reversed });
+    }
+    fn on_insertion_extend(&mut self, pos: usize, len: usize, new_len: usize) {
+        self.events.push(TraceEvent::Extend { pos, len, new_len });
+    }
+    fn on_merge_start(&mut self, run1: Range<usize>, run2: Range<usize>) {
+        self.merge = Some(TraceEvent::Merge {
+            pos: run1.start,
+            first_len: run1.len(),
+            second_len: run2.len(),
+            gallops: 0,
+        });
+    }
+    fn on_gallop_enter(&mut self) {
+        if let Some(TraceEvent::Merge { gallops, .. }) = &mut self.merge {
+            *gallops += 1;
+        }
+    }
+    fn on_merge_end(&mut self, _run: Range<usize>) {
+        self.events.extend(self.merge.take());
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for event in &self.events {
+            match *event {
+                TraceEvent::Run { pos, len, reversed } => {
+                    write!(f, "run {} {}", pos, len)?;
+                    if reversed {
+                        f.write_str(" reversed")?;
+                    }
+                    f.write_str("\n")?;
+                }
+                TraceEvent::Extend { pos, len, new_len } => {
+                    writeln!(f, "extend {} {} {}", pos, len, new_len)?
+                }
+                TraceEvent::Merge {
+                    pos,
+                    first_len,
+                    second_len,
+                    gallops,
+                } => writeln!(f, "merge {} {} {} {}", pos, first_len, second_len, gallops)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The error from parsing a `Trace` that isn't in the text format.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseTraceError {
+    /// The line the error is on, counting from 1.
+    pub line: usize,
+}
+
+impl fmt::Display for ParseTraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid trace on line {}", self.line)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseTraceError {}
+
+impl FromStr for Trace {
+    type Err = ParseTraceError;
+    fn from_str(s: &str) -> Result<Trace, ParseTraceError> {
+        let mut trace = Trace::new();
+        for (i, line) in s.lines().enumerate() {
+            let err = ParseTraceError { line: i + 1 };
+            let mut words = line.split_whitespace();
+            let kind = match words.next() {
+                Some(kind) => kind,
+                None => continue,
+            };
+            let mut num = || -> Result<usize, ParseTraceError> {
+                words
+                    .next()
+                    .and_then(|w| w.parse().ok())
+                    .ok_or_else(|| err.clone())
+            };
+            let event = match kind {
+                "run" => TraceEvent::Run {
+                    pos: num()?,
+                    len: num()?,
+                    reversed: false,
+                },
+                "extend" => TraceEvent::Extend {
+                    pos: num()?,
+                    len: num()?,
+                    new_len: num()?,
+                },
+                "merge" => TraceEvent::Merge {
+                    pos: num()?,
+                    first_len: num()?,
+                    second_len: num()?,
+                    gallops: num()?,
+                },
+                _ => return Err(err),
+            };
+            let event = match (event, words.next()) {
+                (TraceEvent::Run { pos, len, .. }, Some("reversed")) => TraceEvent::Run {
+                    pos,
+                    len,
+                    reversed: true,
+                },
+                (event, None) => event,
+                _ => return Err(err),
+            };
+            trace.events.push(event);
+        }
+        Ok(trace)
+    }
+}
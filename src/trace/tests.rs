@@ -0,0 +1,88 @@
+//! Sort traces.
+
+use super::{ParseTraceError, Trace, TraceEvent};
+use crate::sort_by_observed;
+use crate::test_util::shuffled;
+
+fn traced(list: &mut [u32]) -> Trace {
+    let mut trace = Trace::new();
+    sort_by_observed(list, |a, b| a.cmp(b), &mut trace);
+    trace
+}
+
+#[test]
+fn record() {
+    let mut list: Vec<u32> = (0..256).map(|x| x * 2).collect();
+    list.extend((0..256).rev().map(|x| x * 2 + 1));
+    let trace = traced(&mut list);
+    assert_eq!(
+        trace.events(),
+        [
+            TraceEvent::Run {
+                pos: 0,
+                len: 257,
+                reversed: false
+            },
+            TraceEvent::Run {
+                pos: 257,
+                len: 255,
+                reversed: true
+            },
+            TraceEvent::Merge {
+                pos: 0,
+                first_len: 257,
+                second_len: 255,
+                gallops: 0
+            },
+        ]
+    );
+    assert_eq!(
+        trace.to_string(),
+        "run 0 257\nrun 257 255 reversed\nmerge 0 257 255 0\n"
+    );
+    assert_eq!(
+        trace.to_json(),
+        concat!(
+            r#"{"events":[{"type":"run","pos":0,"len":257,"reversed":false},"#,
+            r#"{"type":"run","pos":257,"len":255,"reversed":true},"#,
+            r#"{"type":"merge","pos":0,"first_len":257,"second_len":255,"gallops":0}]}"#
+        )
+    );
+}
+
+#[test]
+fn gallops() {
+    let mut list: Vec<u32> = (0..8)
+        .flat_map(|i| (0..100).map(move |x| i * 200 + x))
+        .collect();
+    list.extend((0..8).flat_map(|i| (0..100).map(move |x| i * 200 + 100 + x)));
+    let trace = traced(&mut list);
+    assert!(trace.events().iter().any(|e| match *e {
+        TraceEvent::Merge { gallops, .. } => gallops > 0,
+        _ => false,
+    }));
+}
+
+#[test]
+fn round_trip() {
+    let mut list = shuffled(1000);
+    let trace = traced(&mut list);
+    assert!(trace
+        .events()
+        .iter()
+        .any(|e| matches!(e, TraceEvent::Extend { .. })));
+    assert_eq!(trace.to_string().parse(), Ok(trace));
+}
+
+#[test]
+fn parse_errors() {
+    assert_eq!(
+        "run 0 3\n\nmerge 0 1\n".parse::<Trace>(),
+        Err(ParseTraceError { line: 3 })
+    );
+    assert_eq!(
+        "extend 0 1 2 reversed".parse::<Trace>(),
+        Err(ParseTraceError { line: 1 })
+    );
+    assert_eq!("sort 0".parse::<Trace>(), Err(ParseTraceError { line: 1 }));
+}
@@ -0,0 +1,67 @@
+/*
+ * Sorts through the C interface, as a C program would. CI builds the static
+ * library and runs this with
+ *
+ *     cargo rustc --release --features capi --crate-type staticlib
+ *     cc -std=c11 -Wall -Wextra -Werror -Iinclude tests/capi.c \
+ *         target/release/libtimsort.a -lpthread -ldl -lm -o target/capi
+ *     target/capi
+ *
+ * so a header that doesn't match the library fails to compile, link or run.
+ */
+
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "timsort.h"
+
+struct record {
+    int key;
+    unsigned seq;
+};
+
+static int cmp_records(const void *a, const void *b, void *ctx)
+{
+    const struct record *x = a, *y = b;
+    ++*(size_t *)ctx;
+    return (x->key > y->key) - (x->key < y->key);
+}
+
+#define CHECK(cond)                                                         \
+    do {                                                                    \
+        if (!(cond)) {                                                      \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, \
+                    #cond);                                                 \
+            return EXIT_FAILURE;                                            \
+        }                                                                   \
+    } while (0)
+
+int main(void)
+{
+    enum { N = 1000 };
+    static struct record list[N];
+    size_t calls = 0;
+    unsigned i;
+
+    for (i = 0; i < N; i++) {
+        list[i].key = (int)((i * 7919) % 101);
+        list[i].seq = i;
+    }
+    CHECK(timsort_sort(list, N, sizeof list[0], cmp_records, &calls) ==
+          TIMSORT_OK);
+    CHECK(calls > 0);
+    for (i = 1; i < N; i++) {
+        CHECK(list[i - 1].key <= list[i].key);
+        /* Equal keys keep their order. */
+        CHECK(list[i - 1].key < list[i].key || list[i - 1].seq < list[i].seq);
+    }
+
+    CHECK(timsort_sort(NULL, 0, sizeof list[0], cmp_records, &calls) ==
+          TIMSORT_OK);
+    CHECK(timsort_sort(NULL, N, sizeof list[0], cmp_records, &calls) ==
+          TIMSORT_EINVAL);
+    CHECK(timsort_sort(list, N, sizeof list[0], NULL, &calls) ==
+          TIMSORT_EINVAL);
+    CHECK(TIMSORT_ENOMEM != TIMSORT_OK && TIMSORT_ENOMEM != TIMSORT_EINVAL);
+    return EXIT_SUCCESS;
+}